@@ -1,4 +1,5 @@
 use cargo_fixit::core::shell;
+use cargo_fixit::CliError;
 use clap::Parser as _;
 use std::ffi::OsStr;
 
@@ -9,10 +10,12 @@ fn main() {
 
     let args = cli::Command::parse();
 
-    if let Err(err) = args.exec() {
-        shell::error(&err).unwrap();
+    if let Err(err) = args.exec().map_err(CliError::from) {
+        if let Some(error) = &err.error {
+            shell::error(error).unwrap();
+        }
 
-        std::process::exit(101);
+        std::process::exit(err.exit_code);
     }
 }
 
@@ -0,0 +1,110 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::CargoResult;
+
+/// How progress is reported on stdout.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MessageFormat {
+    /// Only human-readable messages on stderr
+    #[default]
+    Human,
+    /// Additionally emit newline-delimited JSON events on stdout
+    Json,
+}
+
+/// A machine-readable record of what fixit did.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum Event<'a> {
+    /// A build unit started being fixed.
+    UnitScheduled {
+        package_id: &'a str,
+        target_kind: &'a str,
+    },
+    /// A suggestion was written to a file.
+    SuggestionApplied {
+        package_id: &'a str,
+        target_kind: &'a str,
+        file: &'a str,
+        code: Option<&'a str>,
+        applicability: &'a str,
+        message: &'a str,
+        replacements: Vec<AppliedReplacement<'a>>,
+    },
+    /// A previously applied suggestion was undone because it broke compilation.
+    SuggestionDropped {
+        package_id: &'a str,
+        target_kind: &'a str,
+        file: &'a str,
+        line: usize,
+        column: usize,
+        code: Option<&'a str>,
+        message: &'a str,
+    },
+    /// A build unit has no more suggestions to apply.
+    UnitFinished {
+        package_id: &'a str,
+        target_kind: &'a str,
+        files: Vec<FixedFile<'a>>,
+    },
+    /// A file was restored to its original contents.
+    FileReverted { file: &'a str },
+    /// Fixes to a file were not applied as it has uncommitted changes.
+    FileSkipped { file: &'a str },
+    /// A diagnostic that was not fixed.
+    DiagnosticRemaining {
+        package_id: &'a str,
+        target_kind: &'a str,
+        code: Option<&'a str>,
+        message: &'a str,
+        rendered: &'a str,
+    },
+}
+
+impl<'a> Event<'a> {
+    /// The package the event is about, if any.
+    pub(crate) fn package_id_mut(&mut self) -> Option<&mut &'a str> {
+        match self {
+            Self::UnitScheduled { package_id, .. }
+            | Self::SuggestionApplied { package_id, .. }
+            | Self::SuggestionDropped { package_id, .. }
+            | Self::UnitFinished { package_id, .. }
+            | Self::DiagnosticRemaining { package_id, .. } => Some(package_id),
+            Self::FileReverted { .. } | Self::FileSkipped { .. } => None,
+        }
+    }
+}
+
+/// Receives the events of a run as they happen.
+pub trait Observer {
+    fn on_event(&mut self, event: &Event<'_>);
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedReplacement<'a> {
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub replacement: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FixedFile<'a> {
+    pub file: &'a str,
+    pub fixes: u32,
+}
+
+impl MessageFormat {
+    /// Write `event` as a line of JSON to stdout, if requested.
+    pub fn emit(self, event: &Event<'_>) -> CargoResult<()> {
+        if self != Self::Json {
+            return Ok(());
+        }
+
+        let mut stdout = anstream::stdout().lock();
+        serde_json::to_writer(&mut stdout, event)?;
+        writeln!(stdout)?;
+        Ok(())
+    }
+}
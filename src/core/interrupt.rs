@@ -0,0 +1,104 @@
+use std::io::Read;
+use std::process::{Child, Command, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::{CargoResult, FixitError};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catches Ctrl-C and termination signals so files can be put back before exiting.
+///
+/// A second signal exits immediately, leaving the files for `--recover`.
+pub(crate) fn install_handler() -> CargoResult<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+    })?;
+    Ok(())
+}
+
+/// Fails with [`FixitError::Interrupted`] once a signal was caught.
+pub(crate) fn check() -> CargoResult<()> {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        Err(FixitError::Interrupted.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Like [`Command::output`], but kills the process when interrupted.
+pub(crate) fn output(command: &mut Command) -> CargoResult<Output> {
+    let mut child = command.spawn()?;
+    let stdout = child.stdout.take().map(read_in_background);
+    let stderr = child.stderr.take().map(read_in_background);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if let Err(error) = check() {
+            stop(&mut child);
+            return Err(error);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    // The same Ctrl-C may have reached the process first, making it exit before the signal was
+    // seen here.
+    check()?;
+    let join = |reader: Option<std::thread::JoinHandle<std::io::Result<Vec<u8>>>>| {
+        reader
+            .map(|reader| reader.join().expect("reader thread panicked"))
+            .transpose()
+            .map(Option::unwrap_or_default)
+    };
+    Ok(Output {
+        status,
+        stdout: join(stdout)?,
+        stderr: join(stderr)?,
+    })
+}
+
+fn read_in_background(
+    mut pipe: impl Read + Send + 'static,
+) -> std::thread::JoinHandle<std::io::Result<Vec<u8>>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        pipe.read_to_end(&mut buf)?;
+        Ok(buf)
+    })
+}
+
+/// How long the child gets to exit on its own before it is killed.
+const GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+fn stop(child: &mut Child) {
+    terminate(child);
+    let start = std::time::Instant::now();
+    while start.elapsed() < GRACE_PERIOD {
+        // Already exited, e.g. from the same Ctrl-C reaching the whole process group.
+        if !matches!(child.try_wait(), Ok(None)) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Asks the child to exit, giving Cargo the chance to stop its own children first.
+#[cfg(unix)]
+fn terminate(child: &Child) {
+    let Ok(pid) = libc::pid_t::try_from(child.id()) else {
+        return;
+    };
+    // SAFETY: `kill` has no memory safety requirements, and `pid` is our child that has not been
+    // waited on yet, so it cannot have been reused by another process.
+    unsafe {
+        libc::kill(pid, libc::SIGTERM);
+    }
+}
+
+/// Windows has no way of asking a process to exit, so it is killed right away.
+#[cfg(not(unix))]
+fn terminate(_child: &Child) {}
@@ -0,0 +1,337 @@
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::CargoResult;
+
+/// What to do with the files modified by a run that did not finish.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Recovery {
+    /// Put back the contents the files had before the run
+    #[default]
+    Restore,
+    /// Keep the fixes that were written and forget the run
+    Finalize,
+}
+
+/// A record on disk of the original contents of every file a run modifies.
+///
+/// Each original is synced to disk before the file is first modified, so a run that is killed
+/// part way through can be rolled back by `--recover`.  The journal is removed once the run
+/// finishes, successfully or not.
+///
+/// ```text
+/// <target-dir>/fixit/journal/
+///   index     one JSON entry per line, appended as files are recorded
+///   0, 1, ..  the original contents
+/// ```
+///
+/// A run that leaves fixes behind, even one that failed part way through, is kept as a [`Run`]
+/// instead, so it can be undone.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    dir: PathBuf,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    path: PathBuf,
+    original: String,
+}
+
+impl Journal {
+    /// Starts a journal for a new run, failing if a previous run never finished.
+    pub(crate) fn open(target_dir: &Path) -> CargoResult<Self> {
+        let dir = journal_dir(target_dir);
+        if dir.join("index").exists() {
+            anyhow::bail!(
+                "a previous `cargo fixit` run was interrupted before it finished\n\
+                run `cargo fixit --recover` to restore the files it modified, \
+                or `cargo fixit --recover=finalize` to keep its fixes"
+            );
+        }
+        Ok(Self {
+            dir,
+            entries: Vec::new(),
+        })
+    }
+
+    /// Finds the journal of an interrupted run, if there is one.
+    pub(crate) fn interrupted(target_dir: &Path) -> CargoResult<Option<Self>> {
+        let dir = journal_dir(target_dir);
+        let Ok(index) = fs::read_to_string(dir.join("index")) else {
+            return Ok(None);
+        };
+        // A line cut short by the interruption belongs to a file that was never modified.
+        let entries = index
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        Ok(Some(Self { dir, entries }))
+    }
+
+    /// Saves the contents of `path` unless it was already recorded by this run.
+    ///
+    /// Must be called before `path` is modified.
+    pub(crate) fn record(&mut self, path: &Path) -> CargoResult<()> {
+        let path = std::path::absolute(path)?;
+        if self.entries.iter().any(|entry| entry.path == path) {
+            return Ok(());
+        }
+        let Ok(original) = fs::read(&path) else {
+            // Nothing to restore for files that don't exist yet.
+            return Ok(());
+        };
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create `{}`", self.dir.display()))?;
+
+        let entry = Entry {
+            path,
+            original: self.entries.len().to_string(),
+        };
+        let mut file = fs::File::create(self.dir.join(&entry.original))?;
+        file.write_all(&original)?;
+        file.sync_all()?;
+
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        let mut index = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join("index"))?;
+        index.write_all(line.as_bytes())?;
+        index.sync_all()?;
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Files whose contents differ from before the run.
+    pub(crate) fn modified_files(&self) -> CargoResult<Vec<&Path>> {
+        let mut files = Vec::new();
+        for entry in &self.entries {
+            let original = fs::read(self.dir.join(&entry.original))?;
+            if fs::read(&entry.path).ok().as_ref() != Some(&original) {
+                files.push(entry.path.as_path());
+            }
+        }
+        Ok(files)
+    }
+
+    /// Writes back the original contents of `path`.
+    pub(crate) fn restore(&self, path: &Path) -> CargoResult<()> {
+        let original = fs::read(self.original_path(path)?)?;
+        cargo_util::paths::write(path, original)
+    }
+
+    /// The contents of `path` before the run.
+    pub(crate) fn original(&self, path: &Path) -> CargoResult<String> {
+        cargo_util::paths::read(&self.original_path(path)?)
+    }
+
+    fn original_path(&self, path: &Path) -> CargoResult<PathBuf> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.path == path)
+            .with_context(|| format!("`{}` is not in the journal", path.display()))?;
+        Ok(self.dir.join(&entry.original))
+    }
+
+    /// Forgets the run, as its files no longer need to be recovered.
+    pub(crate) fn finish(self) -> CargoResult<()> {
+        remove_dir(&self.dir)
+    }
+
+    /// Keeps the before and after contents of the files the run modified for `--undo`.
+    ///
+    /// Returns the id of the run, or `None` if it modified nothing and so was not kept.
+    pub(crate) fn finish_run(self) -> CargoResult<Option<u64>> {
+        let mut files = Vec::new();
+        for entry in &self.entries {
+            let original = fs::read(self.dir.join(&entry.original))?;
+            let Ok(fixed) = fs::read(&entry.path) else {
+                continue;
+            };
+            if fixed == original {
+                continue;
+            }
+            let after = format!("{}.fixed", entry.original);
+            fs::write(self.dir.join(&after), fixed)?;
+            files.push(RunFile {
+                path: entry.path.clone(),
+                before: entry.original.clone(),
+                after,
+            });
+        }
+        if files.is_empty() {
+            self.finish()?;
+            return Ok(None);
+        }
+        fs::write(self.dir.join("run.json"), serde_json::to_vec(&files)?)?;
+
+        let runs_dir = self.dir.with_file_name("runs");
+        fs::create_dir_all(&runs_dir)
+            .with_context(|| format!("failed to create `{}`", runs_dir.display()))?;
+        let id = run_ids(&runs_dir).last().map(|last| last + 1).unwrap_or(1);
+        fs::rename(&self.dir, runs_dir.join(id.to_string()))?;
+
+        let ids = run_ids(&runs_dir);
+        for id in &ids[..ids.len().saturating_sub(KEPT_RUNS)] {
+            remove_dir(&runs_dir.join(id.to_string()))?;
+        }
+        Ok(Some(id))
+    }
+}
+
+/// How many of the latest runs are kept for `--undo`.
+const KEPT_RUNS: usize = 10;
+
+/// A run that left fixes behind, recorded so that they can be undone.
+///
+/// Only the latest [`KEPT_RUNS`] runs are kept, older ones being removed as new ones finish.
+///
+/// ```text
+/// <target-dir>/fixit/runs/<run-id>/
+///   run.json       the files that were fixed
+///   0, 0.fixed ..  the contents before and after the run
+/// ```
+#[derive(Debug)]
+pub(crate) struct Run {
+    id: String,
+    dir: PathBuf,
+    files: Vec<RunFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RunFile {
+    path: PathBuf,
+    before: String,
+    after: String,
+}
+
+impl Run {
+    /// Finds the run with `id`, or the latest run that was not undone yet.
+    pub(crate) fn find(target_dir: &Path, id: Option<&str>) -> CargoResult<Self> {
+        let runs_dir = target_dir.join("fixit").join("runs");
+        let ids = run_ids(&runs_dir);
+        let id = match id {
+            Some(id) if ids.iter().any(|known| known.to_string() == id) => id.to_owned(),
+            Some(id) if ids.is_empty() => {
+                anyhow::bail!("no run `{id}` to undo, as there are no runs that can be undone")
+            }
+            Some(id) => {
+                let known = ids.iter().map(|id| format!("`{id}`")).collect::<Vec<_>>();
+                anyhow::bail!(
+                    "no run `{id}` to undo; runs that can be undone are {}",
+                    known.join(", ")
+                )
+            }
+            None => match ids.last() {
+                Some(id) => id.to_string(),
+                None => anyhow::bail!("no `cargo fixit` run to undo"),
+            },
+        };
+        let dir = runs_dir.join(&id);
+        let files = serde_json::from_slice(&fs::read(dir.join("run.json"))?)
+            .with_context(|| format!("failed to read run `{id}`"))?;
+        Ok(Self { id, dir, files })
+    }
+
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The current contents of each file with the run's fixes undone.
+    ///
+    /// The contents are `None` where the file has changed since in the same places as the fixes.
+    pub(crate) fn undone(&self) -> CargoResult<Vec<(&Path, Option<String>)>> {
+        let mut files = Vec::new();
+        for file in &self.files {
+            let before = cargo_util::paths::read(&self.dir.join(&file.before))?;
+            let after = cargo_util::paths::read(&self.dir.join(&file.after))?;
+            let current = cargo_util::paths::read(&file.path)?;
+            files.push((file.path.as_path(), rebase(&after, &before, &current)));
+        }
+        Ok(files)
+    }
+
+    /// Forgets the run once it was undone.
+    pub(crate) fn finish(self) -> CargoResult<()> {
+        remove_dir(&self.dir)
+    }
+}
+
+/// Makes the changes from `from` to `to` in `current`, an edited copy of `from`, keeping the
+/// other edits.
+///
+/// Returns `None` if `current` was edited at or next to the lines that changed.
+pub(crate) fn rebase(from: &str, to: &str, current: &str) -> Option<String> {
+    if current == from {
+        return Some(to.to_owned());
+    }
+    let fixes = similar::TextDiff::from_lines(from, to);
+    let edits = similar::TextDiff::from_lines(from, current);
+    let to_lines = fixes.iter_new_slices().collect::<Vec<_>>();
+    let current_lines = edits.iter_new_slices().collect::<Vec<_>>();
+    let edits = edits
+        .ops()
+        .iter()
+        .filter(|op| op.tag() != similar::DiffTag::Equal)
+        .collect::<Vec<_>>();
+
+    let mut rebased = String::new();
+    let mut next = 0;
+    for fix in fixes
+        .ops()
+        .iter()
+        .filter(|op| op.tag() != similar::DiffTag::Equal)
+    {
+        let fixed = fix.old_range();
+        if edits.iter().any(|edit| {
+            let edited = edit.old_range();
+            edited.start <= fixed.end && fixed.start <= edited.end
+        }) {
+            return None;
+        }
+        let start = edits
+            .iter()
+            .filter(|edit| edit.old_range().end <= fixed.start)
+            .fold(fixed.start, |start, edit| {
+                start + edit.new_range().len() - edit.old_range().len()
+            });
+        rebased.extend(current_lines[next..start].iter().copied());
+        rebased.extend(to_lines[fix.new_range()].iter().copied());
+        next = start + fixed.len();
+    }
+    rebased.extend(current_lines[next..].iter().copied());
+    Some(rebased)
+}
+
+fn journal_dir(target_dir: &Path) -> PathBuf {
+    target_dir.join("fixit").join("journal")
+}
+
+/// The ids of the runs in `runs_dir`, oldest first.
+fn run_ids(runs_dir: &Path) -> Vec<u64> {
+    let mut ids = fs::read_dir(runs_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids
+}
+
+fn remove_dir(dir: &Path) -> CargoResult<()> {
+    match fs::remove_dir_all(dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("failed to remove `{}`", dir.display()))
+        }
+        _ => Ok(()),
+    }
+}
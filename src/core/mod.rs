@@ -1,2 +1,7 @@
+pub mod events;
+pub(crate) mod interrupt;
+pub(crate) mod journal;
+pub(crate) mod rustfmt;
+pub(crate) mod sandbox;
 pub mod shell;
 pub mod sysroot;
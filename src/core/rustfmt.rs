@@ -0,0 +1,49 @@
+use std::env;
+use std::io::Write as _;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::Context;
+use cargo_util::paths;
+
+use crate::CargoResult;
+
+/// Formats the file at `path` with rustfmt, leaving the modules it declares alone.
+///
+/// The file is passed on stdin from its own directory, so rustfmt still finds the
+/// `rustfmt.toml` that applies to it.
+pub(crate) fn format(path: &Path, edition: &str) -> CargoResult<()> {
+    let rustfmt = env::var_os("RUSTFMT").unwrap_or_else(|| "rustfmt".into());
+    let source = paths::read(path)?;
+    let mut command = Command::new(rustfmt);
+    command
+        .args(["--edition", edition])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // A relative path right in the current directory has an empty parent.
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        command.current_dir(dir);
+    }
+    let mut child = command.spawn().context("failed to run rustfmt")?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // Written from another thread, so a large file cannot fill the pipes both ways.
+    let input = source.clone();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()?;
+    let written = writer.join().expect("writer thread panicked");
+    if !output.status.success() {
+        anyhow::bail!(
+            "rustfmt failed to format `{}`\n{}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+    written?;
+    let formatted = String::from_utf8(output.stdout)
+        .with_context(|| format!("rustfmt returned invalid UTF-8 for `{}`", path.display()))?;
+    if formatted != source {
+        paths::write(path, formatted)?;
+    }
+    Ok(())
+}
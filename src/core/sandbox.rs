@@ -0,0 +1,358 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Context;
+use cargo_metadata::Metadata;
+use cargo_util::paths;
+use cargo_util_schemas::core::PackageIdSpec;
+
+use crate::CargoResult;
+
+/// A temporary copy of the workspace that fixes are applied to instead of the real files.
+#[derive(Debug)]
+pub(crate) struct Sandbox {
+    dir: tempfile::TempDir,
+    workspace_root: PathBuf,
+    /// The copy of the workspace root
+    root: PathBuf,
+    cwd: PathBuf,
+    target_dir: PathBuf,
+    /// Cargo configs above the workspace that Cargo would not find from the copy
+    config_files: Vec<PathBuf>,
+    package_ids: PackageIds,
+}
+
+/// Maps the IDs Cargo gives the packages of a sandbox back to those of the workspace.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PackageIds {
+    /// The IDs of the workspace's packages by the directory of their copy
+    by_copy: HashMap<PathBuf, String>,
+}
+
+impl PackageIds {
+    /// The ID of the workspace package that `package_id` is the copy of, if it is one.
+    pub(crate) fn original(&self, package_id: &str) -> Option<&str> {
+        let spec = PackageIdSpec::parse(package_id).ok()?;
+        let manifest_dir = spec.url()?.to_file_path().ok()?;
+        self.by_copy.get(&manifest_dir).map(String::as_str)
+    }
+}
+
+impl Sandbox {
+    /// Copies the workspace, leaving out the target directory and VCS metadata.
+    ///
+    /// The copy is placed in `<target-dir>/fixit`, where Cargo already writes.  As Cargo and rustup
+    /// look for `.cargo/config.toml` and `rust-toolchain.toml` files from where they run, the
+    /// toolchain file the workspace uses is copied along, and the configs above the workspace that
+    /// Cargo would not find from the copy are passed with `--config`.  Configs found from both
+    /// places are left out of the copy, so they apply once.
+    ///
+    /// The copy is built in `<target-dir>/fixit/sandbox`, as Cargo would otherwise overwrite the
+    /// workspace's build of the same packages with that of the copy.  Path dependencies outside
+    /// the workspace are not copied, but used from where they are.
+    pub(crate) fn new(metadata: &Metadata) -> CargoResult<Self> {
+        let root = metadata.workspace_root.as_std_path().to_owned();
+        let workspace_target_dir = metadata.target_directory.as_std_path();
+        let fixit_dir = workspace_target_dir.join("fixit");
+        let dir = fs::create_dir_all(&fixit_dir)
+            .and_then(|()| {
+                tempfile::Builder::new()
+                    .prefix("copy-")
+                    .tempdir_in(&fixit_dir)
+            })
+            .with_context(|| {
+                format!(
+                    "failed to create a directory for the workspace copy in `{}`",
+                    fixit_dir.display()
+                )
+            })?;
+        let copy = dir.path().join("workspace");
+        let original_cwd = env::current_dir()?;
+        let found_from_copy = config_files(dir.path())
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+        copy_dir(&root, &copy, workspace_target_dir, &found_from_copy)
+            .with_context(|| format!("failed to copy `{}`", root.display()))?;
+        if let Some(toolchain_file) = toolchain_file(&original_cwd) {
+            if !toolchain_file.starts_with(&root) {
+                let file_name = toolchain_file.file_name().unwrap_or_default();
+                fs::copy(&toolchain_file, copy.join(file_name))
+                    .with_context(|| format!("failed to copy `{}`", toolchain_file.display()))?;
+            }
+        }
+        let cargo_home = cargo_home();
+        let mut config_files = config_files(&original_cwd)
+            .into_iter()
+            .filter(|file| {
+                !file.starts_with(&root)
+                    && !found_from_copy.contains(file)
+                    && cargo_home
+                        .as_ref()
+                        .is_none_or(|home| !file.starts_with(home))
+            })
+            .collect::<Vec<_>>();
+        // Later `--config` arguments take precedence, like configs further down the tree.
+        config_files.reverse();
+
+        let manifests = metadata
+            .packages
+            .iter()
+            .map(|package| package.manifest_path.as_std_path().to_owned())
+            .chain(std::iter::once(root.join("Cargo.toml")))
+            .collect::<BTreeSet<_>>();
+        for manifest_path in manifests {
+            let Ok(relative) = manifest_path.strip_prefix(&root) else {
+                continue;
+            };
+            let copied = copy.join(relative);
+            if copied.exists() {
+                remap_path_dependencies(&copied, &manifest_path, &root)?;
+            }
+        }
+
+        let cwd = match original_cwd.strip_prefix(&root) {
+            Ok(relative) => copy.join(relative),
+            Err(_) => copy.clone(),
+        };
+        let target_dir = workspace_target_dir.join("fixit").join("sandbox");
+        let by_copy = metadata
+            .packages
+            .iter()
+            .filter_map(|package| {
+                let manifest_dir = package.manifest_path.parent()?.as_std_path();
+                let relative = manifest_dir.strip_prefix(&root).ok()?;
+                Some((copy.join(relative), package.id.repr.clone()))
+            })
+            .collect();
+
+        Ok(Self {
+            dir,
+            workspace_root: root,
+            root: copy,
+            cwd,
+            target_dir,
+            config_files,
+            package_ids: PackageIds { by_copy },
+        })
+    }
+
+    /// The root of the original workspace.
+    pub(crate) fn workspace_root(&self) -> &Path {
+        &self.workspace_root
+    }
+
+    /// The root of the copied workspace.
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// A directory for the journal of the run, removed along with the copy.
+    pub(crate) fn journal_dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The current directory, translated into the copy.
+    pub(crate) fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// Runs `command` inside the copy, building it in its own target directory.
+    pub(crate) fn configure(&self, command: &mut Command) {
+        command
+            .current_dir(&self.cwd)
+            .env("CARGO_TARGET_DIR", &self.target_dir)
+            // Each copy is in a new place, so its incremental state could never be reused.
+            .env("CARGO_INCREMENTAL", "0");
+    }
+
+    /// Arguments passing the Cargo configs found above the workspace to the Cargo commands fixit
+    /// runs in the copy.
+    pub(crate) fn config_args(&self) -> Vec<String> {
+        self.config_files
+            .iter()
+            .flat_map(|file| ["--config".to_owned(), file.to_string_lossy().into_owned()])
+            .collect()
+    }
+
+    /// Whether a path reported by the compiler belongs to the copy.
+    pub(crate) fn contains(&self, file: &str) -> bool {
+        let path = Path::new(file);
+        path.is_relative() || path.starts_with(&self.root)
+    }
+
+    /// Resolves a path reported by the compiler within the copy.
+    pub(crate) fn path(&self, file: &str) -> PathBuf {
+        self.cwd.join(file)
+    }
+
+    /// Maps the IDs of the copied packages back to the workspace.
+    pub(crate) fn package_ids(&self) -> &PackageIds {
+        &self.package_ids
+    }
+
+    /// Resolves a path reported by the compiler to its counterpart in the original workspace.
+    pub(crate) fn original_path(&self, file: &str) -> PathBuf {
+        match Path::new(file).strip_prefix(&self.root) {
+            Ok(relative) => self.workspace_root.join(relative),
+            Err(_) => PathBuf::from(file),
+        }
+    }
+}
+
+fn copy_dir(
+    from: &Path,
+    to: &Path,
+    target_dir: &Path,
+    skip: &BTreeSet<PathBuf>,
+) -> CargoResult<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path == target_dir || is_vcs_metadata(&entry.file_name()) || skip.contains(&path) {
+            continue;
+        }
+        let dest = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&path, &dest, target_dir, skip)?;
+        } else if file_type.is_file() || path.is_file() {
+            // Symlinked files are copied by content; symlinked directories are skipped to avoid
+            // cycles.
+            fs::copy(&path, &dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// The Cargo configs found from `dir`, nearest first.
+fn config_files(dir: &Path) -> Vec<PathBuf> {
+    dir.ancestors()
+        .filter_map(|dir| {
+            // Cargo prefers `config` when both exist.
+            ["config", "config.toml"]
+                .iter()
+                .map(|name| dir.join(".cargo").join(name))
+                .find(|file| file.is_file())
+        })
+        .collect()
+}
+
+/// The toolchain file rustup would use from `dir`.
+fn toolchain_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find_map(|dir| {
+        ["rust-toolchain", "rust-toolchain.toml"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|file| file.is_file())
+    })
+}
+
+/// Cargo reads the config in its home directory wherever it runs.
+fn cargo_home() -> Option<PathBuf> {
+    env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".cargo")))
+}
+
+/// Points the path dependencies of a copied manifest that lead out of the workspace at their
+/// original location, as they were not copied.
+fn remap_path_dependencies(copied: &Path, manifest_path: &Path, root: &Path) -> CargoResult<()> {
+    let contents = paths::read(copied)?;
+    let mut manifest: toml_edit::DocumentMut = contents
+        .parse()
+        .with_context(|| format!("failed to parse `{}`", manifest_path.display()))?;
+    let manifest_dir = manifest_path.parent().unwrap_or(root);
+    let mut remapped = false;
+    let mut remap = |table: &mut dyn toml_edit::TableLike| {
+        for (_, dependency) in table.iter_mut() {
+            let Some(dependency) = dependency.as_table_like_mut() else {
+                continue;
+            };
+            let Some(path) = dependency.get("path").and_then(|path| path.as_str()) else {
+                continue;
+            };
+            let original = paths::normalize_path(&manifest_dir.join(path));
+            if original.starts_with(root) {
+                continue;
+            }
+            dependency.insert(
+                "path",
+                toml_edit::value(original.to_string_lossy().into_owned()),
+            );
+            remapped = true;
+        }
+    };
+
+    let is_dependencies = |key: &str| {
+        matches!(
+            key,
+            "dependencies"
+                | "dev-dependencies"
+                | "dev_dependencies"
+                | "build-dependencies"
+                | "build_dependencies"
+        )
+    };
+    for (key, item) in manifest.iter_mut() {
+        let Some(table) = item.as_table_like_mut() else {
+            continue;
+        };
+        match key.get() {
+            key if is_dependencies(key) => remap(table),
+            "target" => {
+                for (_, platform) in table.iter_mut() {
+                    let Some(platform) = platform.as_table_like_mut() else {
+                        continue;
+                    };
+                    for (key, item) in platform.iter_mut() {
+                        if let Some(table) = item.as_table_like_mut() {
+                            if is_dependencies(key.get()) {
+                                remap(table);
+                            }
+                        }
+                    }
+                }
+            }
+            "workspace" => {
+                if let Some(table) = table
+                    .get_mut("dependencies")
+                    .and_then(|item| item.as_table_like_mut())
+                {
+                    remap(table);
+                }
+            }
+            "patch" => {
+                for (_, source) in table.iter_mut() {
+                    if let Some(table) = source.as_table_like_mut() {
+                        remap(table);
+                    }
+                }
+            }
+            "replace" => remap(table),
+            _ => {}
+        }
+    }
+    if remapped {
+        paths::write(copied, manifest.to_string())?;
+    }
+    Ok(())
+}
+
+fn is_vcs_metadata(name: &std::ffi::OsStr) -> bool {
+    [
+        ".git",
+        ".hg",
+        ".jj",
+        ".pijul",
+        ".svn",
+        ".fslckout",
+        "_FOSSIL_",
+    ]
+    .iter()
+    .any(|vcs| name == *vcs)
+}
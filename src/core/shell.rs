@@ -2,7 +2,7 @@
 
 use anyhow::Context;
 use clap::builder::styling::Style;
-use clap_cargo::style::{ERROR, HEADER, NOTE, WARN};
+use clap_cargo::style::{ERROR, HEADER, LITERAL, NOTE, UPDATE_ADDED, UPDATE_REMOVED, WARN};
 use std::io::Write;
 
 use crate::CargoResult;
@@ -38,6 +38,70 @@ pub fn fixed(file_name: impl std::fmt::Display, fixes: u32) -> CargoResult<()> {
     )
 }
 
+/// Print a styled unified diff of a file to stdout.
+pub fn diff(file_name: impl std::fmt::Display, original: &str, fixed: &str) -> CargoResult<()> {
+    let mut stdout = anstream::stdout().lock();
+    write_diff(&mut stdout, file_name, original, fixed)
+}
+
+/// Print a styled unified diff of a file to stderr.
+pub fn diff_stderr(
+    file_name: impl std::fmt::Display,
+    original: &str,
+    fixed: &str,
+) -> CargoResult<()> {
+    let mut stderr = anstream::stderr().lock();
+    write_diff(&mut stderr, file_name, original, fixed)
+}
+
+fn write_diff(
+    out: &mut impl Write,
+    file_name: impl std::fmt::Display,
+    original: &str,
+    fixed: &str,
+) -> CargoResult<()> {
+    let diff = similar::TextDiff::from_lines(original, fixed);
+    let old = format!("a/{file_name}");
+    let new = format!("b/{file_name}");
+
+    let bold = Style::new().bold();
+    writeln!(out, "{bold}--- {old}{bold:#}")?;
+    writeln!(out, "{bold}+++ {new}{bold:#}")?;
+    for hunk in diff.unified_diff().iter_hunks() {
+        for line in hunk.to_string().lines() {
+            let style = if line.starts_with("@@") {
+                LITERAL
+            } else if line.starts_with('+') {
+                UPDATE_ADDED
+            } else if line.starts_with('-') {
+                UPDATE_REMOVED
+            } else {
+                Style::new()
+            };
+            writeln!(out, "{style}{line}{style:#}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask a question on stderr and read a line of the answer from stdin.
+///
+/// Returns `None` when stdin is closed.
+pub fn prompt(question: impl std::fmt::Display) -> CargoResult<Option<String>> {
+    {
+        let mut stderr = anstream::stderr().lock();
+        write!(stderr, "{HEADER}?{HEADER:#} {question} ")?;
+        stderr.flush()?;
+    }
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(answer.trim_end_matches(['\n', '\r']).to_owned()))
+}
+
 pub fn print_ansi_stderr(message: &[u8]) -> CargoResult<()> {
     let mut stderr = anstream::stderr().lock();
     stderr.write_all(message)?;
@@ -1,14 +1,74 @@
-use std::{path::PathBuf, process::Command, sync::OnceLock};
+use std::{env, ffi::OsString, path::PathBuf, process::Command, sync::OnceLock};
+
+/// The compiler Cargo builds with, which `RUSTC` overrides.
+pub(crate) fn rustc() -> OsString {
+    env::var_os("RUSTC").unwrap_or_else(|| "rustc".into())
+}
+
+/// The Clippy driver of the toolchain that [`rustc`] belongs to, falling back to the one on `PATH`.
+pub(crate) fn clippy_driver() -> PathBuf {
+    let name = format!("clippy-driver{}", env::consts::EXE_SUFFIX);
+    get_sysroot()
+        .as_ref()
+        .map(|sysroot| sysroot.join("bin").join(&name))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
 
 static SYSROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
 
 pub(crate) fn get_sysroot() -> &'static Option<PathBuf> {
     SYSROOT.get_or_init(|| {
-        Command::new("rustc")
+        Command::new(rustc())
             .arg("--print=sysroot")
             .output()
+            .ok()
+            .filter(|x| x.status.success())
             .map(|x| String::from_utf8_lossy(&x.stdout).trim().to_owned())
             .map(PathBuf::from)
+    })
+}
+
+static HOST_TUPLE: OnceLock<Option<String>> = OnceLock::new();
+
+/// The target triple of the host, which `--target host-tuple` stands for.
+pub(crate) fn get_host_tuple() -> &'static Option<String> {
+    HOST_TUPLE.get_or_init(|| {
+        Command::new(rustc())
+            .arg("--print=host-tuple")
+            .output()
             .ok()
+            .filter(|x| x.status.success())
+            .map(|x| String::from_utf8_lossy(&x.stdout).trim().to_owned())
     })
 }
+
+/// Whether the standard library for `target` is installed.
+///
+/// Targets the compiler does not know are reported as installed, for Cargo to report the error.
+pub(crate) fn is_target_installed(target: &str) -> bool {
+    let Ok(output) = Command::new(rustc())
+        .args(["--print=target-libdir", "--target", target])
+        .output()
+    else {
+        return true;
+    };
+    if !output.status.success() {
+        return true;
+    }
+    let libdir = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    libdir.is_dir()
+}
+
+/// Whether `rustup target add` can install the standard library for `target`.
+///
+/// Tier 3 targets are not shipped by rustup, and neither is anything without rustup.
+pub(crate) fn rustup_ships_target(target: &str) -> bool {
+    let Ok(output) = Command::new("rustup").args(["target", "list"]).output() else {
+        return false;
+    };
+    output.status.success()
+        && String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.split_whitespace().next() == Some(target))
+}
@@ -6,6 +6,111 @@ pub type Error = anyhow::Error;
 
 pub use anyhow::Context;
 
+/// The failures of a run that callers may want to tell apart.
+///
+/// These are returned inside an [`Error`], which can be downcast to them.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FixitError {
+    /// No version control system was found to protect the files from destructive changes
+    NoVcs,
+    /// The working directory has uncommitted changes
+    VcsDirty {
+        /// Files with unstaged changes, relative to the root of the repository
+        dirty: Vec<String>,
+        /// Files with staged changes, relative to the root of the repository
+        staged: Vec<String>,
+    },
+    /// The workspace could not be loaded
+    Metadata(cargo_metadata::Error),
+    /// The code did not compile before any fixes were applied
+    CompileFailed {
+        /// The errors as printed by the compiler
+        errors: Vec<String>,
+    },
+    /// The code stopped compiling after fixes were applied, so the files were reverted
+    FixesBrokeBuild {
+        /// The files that were put back to their original contents
+        reverted: Vec<String>,
+        /// The errors as printed by the compiler after the fixes were applied
+        errors: Vec<String>,
+    },
+    /// Cargo failed without reporting any diagnostics, like when it was killed
+    CargoTerminated {
+        /// `None` when Cargo was stopped by a signal
+        exit_code: Option<i32>,
+    },
+    /// The `--verify` command failed after the fixes were applied, so they were reverted
+    VerifyFailed {
+        /// The command as given to `--verify`
+        command: String,
+        /// The files that were put back to their original contents
+        reverted: Vec<String>,
+    },
+    /// The run was stopped by Ctrl-C or a termination signal
+    Interrupted,
+}
+
+impl FixitError {
+    /// The exit code of `cargo fixit` for this failure.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NoVcs => 3,
+            Self::VcsDirty { .. } => 4,
+            Self::Metadata(_) => 5,
+            Self::CompileFailed { .. } => 6,
+            Self::FixesBrokeBuild { .. } => 7,
+            Self::CargoTerminated { .. } => 8,
+            Self::VerifyFailed { .. } => 9,
+            Self::Interrupted => 130,
+        }
+    }
+}
+
+impl std::fmt::Display for FixitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoVcs => f.write_str(
+                "no VCS found for this package and `cargo fix` can potentially \
+                perform destructive changes; if you'd like to suppress this \
+                error pass `--allow-no-vcs`",
+            ),
+            Self::VcsDirty { dirty, staged } => {
+                f.write_str(
+                    "the working directory of this package has uncommitted changes, and \
+                    `cargo fix` can potentially perform destructive changes; if you'd \
+                    like to suppress this error pass `--allow-dirty`, \
+                    or commit the changes to these files:\n\n",
+                )?;
+                for file in dirty {
+                    writeln!(f, "  * {file} (dirty)")?;
+                }
+                for file in staged {
+                    writeln!(f, "  * {file} (staged)")?;
+                }
+                f.write_str("\n")
+            }
+            Self::Metadata(_) => f.write_str("failed to run `cargo metadata`"),
+            Self::CompileFailed { .. }
+            | Self::FixesBrokeBuild { .. }
+            | Self::CargoTerminated { .. } => f.write_str("could not compile"),
+            Self::VerifyFailed { command, .. } => {
+                write!(f, "`{command}` failed after the fixes were applied")
+            }
+            Self::Interrupted => f.write_str("interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for FixitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Metadata(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
 /// CLI-specific result
 pub type CliResult = Result<(), CliError>;
 
@@ -46,7 +151,11 @@ impl CliError {
 
 impl From<anyhow::Error> for CliError {
     fn from(err: anyhow::Error) -> CliError {
-        CliError::new(err, 101)
+        let code = err
+            .downcast_ref::<FixitError>()
+            .map(FixitError::exit_code)
+            .unwrap_or(101);
+        CliError::new(err, code)
     }
 }
 
@@ -1,3 +1,6 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
 use rustfix::diagnostics::Diagnostic;
 use serde::Deserialize;
 
@@ -22,6 +25,8 @@ impl CheckOutput {
 pub struct Artifact {
     #[serde(flatten)]
     pub build_unit: BuildUnit,
+    #[serde(default)]
+    pub filenames: Vec<String>,
     pub fresh: bool,
 }
 
@@ -52,6 +57,98 @@ pub enum DiagnosticLevel {
 pub struct BuildUnit {
     pub package_id: String,
     pub target: Target,
+    /// Only reported by Cargo for artifacts, see [`attribute_units`]
+    #[serde(default)]
+    pub profile: Profile,
+    /// The target triple the unit was built for, or `None` for the host
+    #[serde(skip)]
+    pub platform: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Profile {
+    /// Built with `--test`, as for unit tests, integration tests and benchmarks
+    pub test: bool,
+}
+
+/// Fills in the profile and platform of the units that messages came from.
+///
+/// Cargo only reports them for artifacts, which follow the messages of their unit.  When a target
+/// is built more than once at the same time, like a library and its unit tests, messages may be
+/// attributed to the wrong one of them, unless they were replayed, see [`needs_replay`].
+///
+/// `platforms` are the target triples that were requested, as named in the target directory.
+pub fn attribute_units(outputs: &mut [CheckOutput], platforms: &[String]) {
+    let mut pending: HashMap<(String, Target), Vec<usize>> = HashMap::new();
+    for i in 0..outputs.len() {
+        match &mut outputs[i] {
+            CheckOutput::Message(message) => {
+                let unit = &mut message.build_unit;
+                // For units that failed to build and so have no artifact
+                unit.profile.test = unit
+                    .target
+                    .kind
+                    .iter()
+                    .any(|kind| matches!(kind, TargetKind::Test | TargetKind::Bench));
+                pending
+                    .entry((unit.package_id.clone(), unit.target.clone()))
+                    .or_default()
+                    .push(i);
+            }
+            CheckOutput::Artifact(artifact) => {
+                artifact.build_unit.platform = artifact.filenames.first().and_then(|file| {
+                    Path::new(file).components().find_map(|component| {
+                        let component = component.as_os_str();
+                        platforms
+                            .iter()
+                            .find(|platform| component == platform.as_str())
+                            .cloned()
+                    })
+                });
+                let unit = artifact.build_unit.clone();
+                let key = (unit.package_id.clone(), unit.target.clone());
+                for j in pending.remove(&key).unwrap_or_default() {
+                    if let CheckOutput::Message(message) = &mut outputs[j] {
+                        message.build_unit = unit.clone();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether a target was built more than once in this run, so that the messages of its units may
+/// have interleaved.
+///
+/// Cargo replays the messages of fresh units one unit at a time, each followed by its artifact,
+/// so running again once everything is built tells them apart.
+pub fn needs_replay(outputs: &[CheckOutput]) -> bool {
+    let mut units: HashMap<(&str, &Target), (usize, bool)> = HashMap::new();
+    for output in outputs {
+        if let CheckOutput::Artifact(artifact) = output {
+            let unit = &artifact.build_unit;
+            let (count, built) = units.entry((&unit.package_id, &unit.target)).or_default();
+            *count += 1;
+            *built |= !artifact.fresh;
+        }
+    }
+    units.values().any(|(count, built)| 1 < *count && *built)
+}
+
+/// Marks the artifacts of a replay that were built by the run before it.
+pub fn keep_freshness(replay: &mut [CheckOutput], outputs: &[CheckOutput]) {
+    let built = outputs
+        .iter()
+        .filter_map(|output| match output {
+            CheckOutput::Artifact(artifact) if !artifact.fresh => Some(&artifact.filenames),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    for output in replay {
+        if let CheckOutput::Artifact(artifact) = output {
+            artifact.fresh = !built.contains(&artifact.filenames);
+        }
+    }
 }
 
 #[derive(Deserialize, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -78,6 +175,19 @@ pub enum TargetKind {
     Lib(CrateType),
 }
 
+impl TargetKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bin => "bin",
+            Self::Test => "test",
+            Self::Bench => "bench",
+            Self::Example => "example",
+            Self::CustomBuild => "custom-build",
+            Self::Lib(_) => "lib",
+        }
+    }
+}
+
 #[derive(Deserialize, Hash, PartialEq, Clone, Eq, Debug, PartialOrd, Ord)]
 #[serde(rename_all(deserialize = "kebab-case"))]
 pub enum CrateType {
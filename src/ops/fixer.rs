@@ -0,0 +1,321 @@
+//! Driving fixes from other tools, without going through the command line.
+
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::format_err;
+use clap::Parser as _;
+
+use crate::core::events::{Event, Observer};
+use crate::ops::fixit::FixitArgs;
+use crate::util::applicability::Applicability;
+use crate::CargoResult;
+
+/// Fixes the warnings in a workspace, like `cargo fixit`.
+///
+/// Progress is printed to stderr as with the command, and reported to an [`Observer`] if one is
+/// set.
+///
+/// ```no_run
+/// use cargo_fixit::ops::{Driver, Fixer};
+///
+/// let report = Fixer::new()
+///     .manifest_path("path/to/Cargo.toml")
+///     .driver(Driver::Clippy)
+///     .lint("clippy::style")
+///     .fix()?;
+/// for file in &report.files {
+///     println!("{}: {} fixes", file.path.display(), file.fixes);
+/// }
+/// # Ok::<(), cargo_fixit::Error>(())
+/// ```
+#[derive(Default)]
+pub struct Fixer {
+    /// The options as they would be given to `cargo fixit`, so the command line's rules for
+    /// combining them apply
+    args: Vec<OsString>,
+    observer: Option<Box<dyn Observer>>,
+}
+
+/// The compiler driver that reports the warnings to fix.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Driver {
+    /// `cargo check`
+    #[default]
+    Check,
+    /// `cargo clippy`
+    Clippy,
+}
+
+/// Targets to fix within the selected packages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Lib,
+    Bins,
+    Bin(String),
+    Examples,
+    Example(String),
+    Tests,
+    Test(String),
+    Benches,
+    Bench(String),
+    All,
+}
+
+impl Fixer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The workspace or package to fix, instead of the one in the current directory
+    pub fn manifest_path(self, path: impl Into<PathBuf>) -> Self {
+        self.arg("--manifest-path").arg(path.into())
+    }
+
+    /// Fix this package, given as a package ID spec
+    pub fn package(self, spec: impl Into<String>) -> Self {
+        self.arg("--package").arg(spec.into())
+    }
+
+    /// Fix every package in the workspace
+    pub fn workspace(self) -> Self {
+        self.arg("--workspace")
+    }
+
+    /// Leave out this package when fixing the workspace
+    pub fn exclude(self, spec: impl Into<String>) -> Self {
+        self.arg("--exclude").arg(spec.into())
+    }
+
+    /// Fix these targets
+    pub fn target(self, target: Target) -> Self {
+        match target {
+            Target::Lib => self.arg("--lib"),
+            Target::Bins => self.arg("--bins"),
+            Target::Bin(name) => self.arg("--bin").arg(name),
+            Target::Examples => self.arg("--examples"),
+            Target::Example(name) => self.arg("--example").arg(name),
+            Target::Tests => self.arg("--tests"),
+            Target::Test(name) => self.arg("--test").arg(name),
+            Target::Benches => self.arg("--benches"),
+            Target::Bench(name) => self.arg("--bench").arg(name),
+            Target::All => self.arg("--all-targets"),
+        }
+    }
+
+    /// Activate this feature
+    pub fn feature(self, feature: impl Into<String>) -> Self {
+        self.arg("--features").arg(feature.into())
+    }
+
+    /// Activate all available features
+    pub fn all_features(self) -> Self {
+        self.arg("--all-features")
+    }
+
+    /// Do not activate the `default` feature
+    pub fn no_default_features(self) -> Self {
+        self.arg("--no-default-features")
+    }
+
+    /// Fix each package without features, with each feature on its own, and with all of them
+    pub fn each_feature(self) -> Self {
+        self.arg("--each-feature")
+    }
+
+    /// Fix each package with every combination of at most `depth` of its features, two by default
+    pub fn feature_powerset(self, depth: Option<usize>) -> Self {
+        let fixer = self.arg("--feature-powerset");
+        match depth {
+            Some(depth) => fixer.arg("--depth").arg(depth.to_string()),
+            None => fixer,
+        }
+    }
+
+    /// The compiler driver whose warnings are fixed, instead of the one the workspace configures
+    pub fn driver(self, driver: Driver) -> Self {
+        match driver {
+            Driver::Check => self.arg("--no-clippy"),
+            Driver::Clippy => self.arg("--clippy"),
+        }
+    }
+
+    /// Apply suggestions up to this level of confidence
+    pub fn applicability(self, applicability: Applicability) -> Self {
+        self.arg("--applicability").arg(applicability.as_str())
+    }
+
+    /// Also try `maybe-incorrect` suggestions, keeping each only if the code still compiles
+    /// without new warnings
+    pub fn speculative(self) -> Self {
+        self.arg("--speculative")
+    }
+
+    /// Only fix this lint, with lint groups and globs like `clippy::style` supported
+    pub fn lint(self, lint: impl Into<String>) -> Self {
+        self.arg("--lint").arg(lint.into())
+    }
+
+    /// Do not fix this lint, with lint groups and globs like `clippy::needless_*` supported
+    pub fn skip_lint(self, lint: impl Into<String>) -> Self {
+        self.arg("--skip-lint").arg(lint.into())
+    }
+
+    /// Format the fixed files with rustfmt
+    pub fn rustfmt(self) -> Self {
+        self.arg("--fmt")
+    }
+
+    /// Run this command once fixing is done, reverting the fixes if it fails
+    pub fn verify(self, command: impl Into<String>) -> Self {
+        self.arg("--verify").arg(command.into())
+    }
+
+    /// Only revert the files whose fixes make the [`Fixer::verify`] command fail
+    pub fn bisect(self) -> Self {
+        self.arg("--bisect")
+    }
+
+    /// Fix code even if it already has compiler errors
+    pub fn broken_code(self) -> Self {
+        self.arg("--broken-code")
+    }
+
+    /// Fix code even if the working directory has uncommitted changes
+    pub fn allow_dirty(self) -> Self {
+        self.arg("--allow-dirty")
+    }
+
+    /// Fix code even if the working directory has staged changes
+    pub fn allow_staged(self) -> Self {
+        self.arg("--allow-staged")
+    }
+
+    /// Fix code even if no version control system was found
+    pub fn allow_no_vcs(self) -> Self {
+        self.arg("--allow-no-vcs")
+    }
+
+    /// Report the events of the run to `observer` as they happen
+    pub fn observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Applies the fixes, reporting what was done.
+    pub fn fix(self) -> CargoResult<FixReport> {
+        let mut args = FixitArgs::try_parse_from(std::iter::once("fixit".into()).chain(self.args))
+            .map_err(invalid_options)?;
+        let report = Rc::new(RefCell::new(FixReport::default()));
+        args.set_observer(Box::new(Reporter {
+            report: report.clone(),
+            observer: self.observer,
+        }));
+        args.run()?;
+        Ok(report.take())
+    }
+}
+
+/// Reports options the command line would reject, without its usage and help hints.
+fn invalid_options(error: clap::Error) -> anyhow::Error {
+    let message = error.to_string();
+    let message = message.split("\n\n").next().unwrap_or_default();
+    format_err!("{}", message.strip_prefix("error: ").unwrap_or(message))
+}
+
+/// What a [`Fixer`] did.
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct FixReport {
+    /// The files that were fixed
+    pub files: Vec<FileFixes>,
+    /// Diagnostics that were left unfixed
+    pub remaining: Vec<RemainingDiagnostic>,
+    /// Files that were not fixed as they have uncommitted changes
+    pub skipped: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileFixes {
+    /// The absolute path
+    pub path: PathBuf,
+    pub package_id: String,
+    pub fixes: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RemainingDiagnostic {
+    pub package_id: String,
+    pub target_kind: String,
+    /// The lint or error code
+    pub code: Option<String>,
+    pub message: String,
+    /// The diagnostic as printed by the compiler
+    pub rendered: String,
+}
+
+/// Builds the report from the events of a run, passing them on to the user's observer.
+struct Reporter {
+    report: Rc<RefCell<FixReport>>,
+    observer: Option<Box<dyn Observer>>,
+}
+
+impl Observer for Reporter {
+    fn on_event(&mut self, event: &Event<'_>) {
+        let mut report = self.report.borrow_mut();
+        match event {
+            Event::UnitFinished {
+                package_id, files, ..
+            } => {
+                for file in files {
+                    let path = absolute(file.file);
+                    match report.files.iter_mut().find(|fixed| fixed.path == path) {
+                        Some(fixed) => fixed.fixes += file.fixes,
+                        None => report.files.push(FileFixes {
+                            path,
+                            package_id: (*package_id).to_owned(),
+                            fixes: file.fixes,
+                        }),
+                    }
+                }
+            }
+            Event::DiagnosticRemaining {
+                package_id,
+                target_kind,
+                code,
+                message,
+                rendered,
+            } => report.remaining.push(RemainingDiagnostic {
+                package_id: (*package_id).to_owned(),
+                target_kind: (*target_kind).to_owned(),
+                code: code.map(ToOwned::to_owned),
+                message: (*message).to_owned(),
+                rendered: (*rendered).to_owned(),
+            }),
+            Event::FileReverted { file } => {
+                let path = absolute(file);
+                report.files.retain(|fixed| fixed.path != path);
+            }
+            Event::FileSkipped { file } => report.skipped.push(absolute(file)),
+            _ => {}
+        }
+        drop(report);
+        if let Some(observer) = &mut self.observer {
+            observer.on_event(event);
+        }
+    }
+}
+
+/// Event paths are relative to the current directory, which may change before the report is read.
+fn absolute(file: &str) -> PathBuf {
+    std::path::absolute(file).unwrap_or_else(|_| PathBuf::from(file))
+}
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
@@ -7,10 +9,12 @@ use std::io::BufRead;
 use std::io::BufReader;
 use std::io::Cursor;
 use std::path::Path;
+use std::path::PathBuf;
 use std::process::Command;
 use std::process::Stdio;
 
 use anyhow::Context;
+use cargo_metadata::Edition;
 use cargo_metadata::Metadata;
 use cargo_metadata::MetadataCommand;
 use cargo_util::paths;
@@ -18,31 +22,129 @@ use cargo_util_schemas::core::PackageIdSpec;
 use clap::ArgAction;
 use clap::Parser;
 use indexmap::{IndexMap, IndexSet};
+use rustfix::diagnostics::Diagnostic;
 use rustfix::{collect_suggestions, CodeFix, Suggestion};
 use tracing::{trace, warn};
 
 use crate::util::cli::PackageSelection;
 use crate::{
-    core::{shell, sysroot::get_sysroot},
+    core::{
+        events::{AppliedReplacement, Event, FixedFile, MessageFormat, Observer},
+        interrupt,
+        journal::{rebase, Journal, Recovery, Run},
+        rustfmt,
+        sandbox::{PackageIds, Sandbox},
+        shell,
+        sysroot::{get_sysroot, rustup_ships_target},
+    },
     ops::check::{
-        BuildUnit, CheckOutput, CrateType, DiagnosticLevel, Message, MessageDiagnostic, TargetKind,
+        attribute_units, keep_freshness, needs_replay, BuildUnit, CheckOutput, CrateType,
+        DiagnosticLevel, Message, MessageDiagnostic, TargetKind,
     },
+    ops::review::{Candidate, Reviewer},
+    ops::verify::VerifyCommand,
     util::{
-        cli::CheckFlags, messages::gen_please_report_this_bug_text, package::format_package_id,
-        vcs::VcsOpts,
+        applicability::Applicability,
+        cli::CheckFlags,
+        config::{ExcludedPaths, WorkspaceConfig},
+        edition,
+        features::FeatureMatrix,
+        lints::{LintFilter, LintOpts},
+        messages::gen_please_report_this_bug_text,
+        package::format_package_id,
+        vcs::{ChangedLines, CommitGrouping, Committer, VcsOpts},
     },
-    CargoResult,
+    CargoResult, FixitError,
 };
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Default, Parser)]
 pub struct FixitArgs {
     /// Run `clippy` instead of `check`
-    #[arg(long)]
-    clippy: bool,
+    #[arg(long, overrides_with = "no_clippy")]
+    pub(crate) clippy: bool,
+
+    /// Run `check` even if the workspace is configured to run `clippy`
+    #[arg(long, overrides_with = "clippy")]
+    pub(crate) no_clippy: bool,
 
     /// Fix code even if it already has compiler errors
     #[arg(long)]
-    broken_code: bool,
+    pub(crate) broken_code: bool,
+
+    /// Show the fixes as a diff without writing them to disk
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Fix a copy of the workspace, then apply the fixes, or save them as a patch to this path
+    #[arg(
+        long,
+        value_name = "PATCH",
+        conflicts_with_all = ["dry_run", "recover", "undo", "commit"]
+    )]
+    sandbox: Option<Option<PathBuf>>,
+
+    /// Format the fixed files with rustfmt
+    #[arg(long)]
+    pub(crate) fmt: bool,
+
+    /// Fix in preparation for the next edition
+    #[arg(long)]
+    edition: bool,
+
+    /// Fix warnings to migrate to the idioms of an edition
+    #[arg(long)]
+    edition_idioms: bool,
+
+    /// Ask before applying each suggestion
+    #[arg(long)]
+    interactive: bool,
+
+    /// Also try `maybe-incorrect` suggestions, keeping each only if the code still compiles
+    /// without new warnings
+    #[arg(long, conflicts_with = "broken_code")]
+    pub(crate) speculative: bool,
+
+    /// Recover the files of a run that was interrupted before it finished
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ACTION",
+        num_args = 0..=1,
+        default_missing_value = "restore",
+        conflicts_with = "dry_run"
+    )]
+    recover: Option<Recovery>,
+
+    /// Undo the fixes of the latest run, or of the run with this id
+    ///
+    /// The id of each run is printed once it finishes.  The last 10 runs can be undone.
+    #[arg(
+        long,
+        value_name = "RUN_ID",
+        conflicts_with_all = ["dry_run", "recover"]
+    )]
+    undo: Option<Option<String>>,
+
+    /// What to do with the fixes written so far on Ctrl-C or a termination signal
+    #[arg(long, value_enum, value_name = "ACTION", default_value_t)]
+    on_interrupt: Recovery,
+
+    /// Apply suggestions up to this level of confidence
+    #[arg(long, value_enum, value_name = "LEVEL")]
+    pub(crate) applicability: Option<Applicability>,
+
+    /// Run this command once fixing is done, reverting the fixes if it fails
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        value_parser = VerifyCommand::parse,
+        conflicts_with_all = ["dry_run", "commit"]
+    )]
+    pub(crate) verify: Option<VerifyCommand>,
+
+    /// Only revert the files whose fixes make the `--verify` command fail
+    #[arg(long, requires = "verify")]
+    pub(crate) bisect: bool,
 
     /// Fix all targets together, risking stale suggestions
     #[arg(long = "Zdangerous-parallel-fixes")]
@@ -52,25 +154,94 @@ pub struct FixitArgs {
     color: colorchoice_clap::Color,
 
     #[command(flatten)]
-    vcs_opts: VcsOpts,
+    pub(crate) vcs_opts: VcsOpts,
+
+    #[command(flatten)]
+    pub(crate) lint_opts: LintOpts,
 
     #[command(flatten)]
-    check_flags: CheckFlags,
+    pub(crate) check_flags: CheckFlags,
+
+    /// Output format for reporting fixes on stdout
+    #[arg(long, value_enum, value_name = "FMT", default_value_t)]
+    message_format: MessageFormat,
 
     #[arg(long, action = ArgAction::Count)]
     verbose: u8,
+
+    /// The root of the workspace, which the compiler reports paths relative to
+    #[arg(skip)]
+    workspace_root: PathBuf,
+
+    /// Defaults from `fixit.toml` or `[workspace.metadata.fixit]`
+    #[arg(skip)]
+    config: WorkspaceConfig,
+
+    /// Maps the package IDs of a sandbox back to the workspace in events
+    #[arg(skip)]
+    package_ids: PackageIds,
+
+    #[arg(skip)]
+    observer: Option<ObserverCell>,
+}
+
+/// Lets an [`Observer`] be called through the shared [`FixitArgs`].
+struct ObserverCell(RefCell<Box<dyn Observer>>);
+
+impl std::fmt::Debug for ObserverCell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ObserverCell").finish_non_exhaustive()
+    }
 }
 
 impl FixitArgs {
     pub fn exec(self) -> CargoResult<()> {
+        interrupt::install_handler()?;
+        exec(self)
+    }
+
+    /// Runs without taking over Ctrl-C, for use as a library.
+    pub(crate) fn run(self) -> CargoResult<()> {
         exec(self)
     }
 
-    fn to_command(&self) -> Command {
-        let cmd = if self.clippy { "clippy" } else { "check" };
+    pub(crate) fn set_observer(&mut self, observer: Box<dyn Observer>) {
+        self.observer = Some(ObserverCell(RefCell::new(observer)));
+    }
+
+    fn emit(&self, event: &Event<'_>) -> CargoResult<()> {
+        let mut event = event.clone();
+        if let Some(package_id) = event.package_id_mut() {
+            if let Some(original) = self.package_ids.original(package_id) {
+                *package_id = original;
+            }
+        }
+        let event = &event;
+        if let Some(observer) = &self.observer {
+            observer.0.borrow_mut().on_event(event);
+        }
+        self.message_format.emit(event)
+    }
+
+    fn clippy(&self) -> bool {
+        self.clippy || (!self.no_clippy && self.config.clippy())
+    }
+
+    fn applicability(&self) -> Applicability {
+        self.applicability
+            .or(self.config.applicability())
+            .unwrap_or(Applicability::Machine)
+    }
+
+    fn to_command(&self, sandbox: Option<&Sandbox>) -> Command {
+        let cmd = if self.clippy() { "clippy" } else { "check" };
         let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
         let mut command = Command::new(cargo);
         command.arg(cmd).args(self.check_flags.to_flags());
+        if let Some(sandbox) = sandbox {
+            command.args(sandbox.config_args());
+            sandbox.configure(&mut command);
+        }
         command
     }
 }
@@ -84,45 +255,805 @@ struct ActiveState {
 #[derive(Debug, Default)]
 struct File {
     fixes: u32,
+    /// Fixes that were not machine-applicable
+    risky_fixes: u32,
     original_source: String,
 }
 
-type BuildUnitErrors = IndexMap<UnitId, IndexSet<String>>;
-type BuildUnitSuggestions =
-    IndexMap<UnitId, IndexMap<String, IndexSet<(Suggestion, Option<String>)>>>;
+/// A suggestion to apply, along with the diagnostic it came from.
+#[derive(Debug, Hash, PartialEq, Eq)]
+struct FixSuggestion {
+    suggestion: Suggestion,
+    applicability: Applicability,
+    /// Riskier than the applicability asked for, so only kept if the unit still compiles cleanly
+    speculative: bool,
+    code: Option<String>,
+    rendered: Option<String>,
+}
+
+/// Identifies a suggestion independently of where it currently sits in its files.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct SuggestionKey {
+    code: Option<String>,
+    message: String,
+    /// The file, replaced text, and replacement for every edit of every solution
+    edits: Vec<(String, String, String)>,
+}
+
+impl SuggestionKey {
+    /// `sources` must hold the current contents of every file the suggestion edits.
+    pub(crate) fn new(
+        sources: &IndexMap<String, String>,
+        code: Option<&str>,
+        suggestion: &Suggestion,
+    ) -> Self {
+        let edits = suggestion
+            .solutions
+            .iter()
+            .flat_map(|solution| &solution.replacements)
+            .map(|replacement| {
+                let file = &replacement.snippet.file_name;
+                let original = sources
+                    .get(file)
+                    .and_then(|source| source.get(replacement.snippet.range.clone()))
+                    .unwrap_or_default();
+                (
+                    file.clone(),
+                    original.to_owned(),
+                    replacement.replacement.clone(),
+                )
+            })
+            .collect();
+        Self {
+            code: code.map(ToOwned::to_owned),
+            message: suggestion.message.clone(),
+            edits,
+        }
+    }
+}
+
+/// A diagnostic that is reported back to the user rather than fixed.
+#[derive(Debug, Hash, PartialEq, Eq)]
+struct UnfixedDiagnostic {
+    code: Option<String>,
+    message: String,
+    rendered: String,
+}
+
+impl FixSuggestion {
+    fn to_unfixed(&self) -> Option<UnfixedDiagnostic> {
+        Some(UnfixedDiagnostic {
+            code: self.code.clone(),
+            message: self.suggestion.message.clone(),
+            rendered: self.rendered.clone()?,
+        })
+    }
+}
+
+impl UnfixedDiagnostic {
+    fn new(diagnostic: Diagnostic) -> Option<Self> {
+        Some(Self {
+            code: diagnostic.code.map(|code| code.code),
+            message: diagnostic.message,
+            rendered: diagnostic.rendered?,
+        })
+    }
+}
+
+/// The files a run fixed, along with the package that fixed them.
+type FixedFiles = IndexMap<String, Fixed>;
+
+#[derive(Debug)]
+struct Fixed {
+    package_id: String,
+    fixes: u32,
+}
+
+/// What a run of fixes did.
+#[derive(Debug, Default)]
+struct Pass {
+    fixed: FixedFiles,
+    /// How many diagnostics were left unfixed
+    remaining: usize,
+}
+
+type BuildUnitErrors = IndexMap<UnitId, IndexSet<UnfixedDiagnostic>>;
+type BuildUnitSuggestions = IndexMap<UnitId, IndexMap<String, IndexSet<FixSuggestion>>>;
 
 #[tracing::instrument(skip_all)]
-fn exec(args: FixitArgs) -> CargoResult<()> {
+fn exec(mut args: FixitArgs) -> CargoResult<()> {
     args.color.write_global();
 
-    args.vcs_opts.valid_vcs()?;
+    if let Some(recovery) = args.recover {
+        return recover(&args, recovery);
+    }
+    if let Some(run_id) = &args.undo {
+        return undo(&args, run_id.as_deref());
+    }
+    let metadata = package_metadata(&args.check_flags, None)?;
+    args.workspace_root = metadata.workspace_root.clone().into_std_path_buf();
+    args.config = WorkspaceConfig::load(&metadata)?;
+    if args.applicability.or(args.config.applicability()).is_none()
+        && env::var_os("__CARGO_FIX_YOLO").is_some()
+    {
+        shell::warn(
+            "`__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead",
+        )?;
+        args.applicability = Some(Applicability::Unspecified);
+    }
+    for target in args.check_flags.remove_uninstalled_targets() {
+        shell::warn(format!(
+            "skipping `--target {target}` as its standard library is not installed"
+        ))?;
+        if rustup_ships_target(&target) {
+            shell::note(format!(
+                "run `rustup target add {target}` to fix it as well"
+            ))?;
+        }
+    }
+    let (sandbox, mut journal) = if args.dry_run {
+        let sandbox = Sandbox::new(&metadata)?;
+        args.check_flags
+            .rebase_manifest_path(sandbox.workspace_root(), sandbox.root());
+        (Some(sandbox), None)
+    } else if args.sandbox.is_some() {
+        // Edits to the workspace made during the run are kept when handing back the fixes, so
+        // there is no need to check for uncommitted changes.
+        let sandbox = Sandbox::new(&metadata)?;
+        args.check_flags
+            .rebase_manifest_path(sandbox.workspace_root(), sandbox.root());
+        let journal = Journal::open(sandbox.journal_dir())?;
+        (Some(sandbox), Some(journal))
+    } else {
+        let journal = Journal::open(metadata.target_directory.as_std_path())?;
+        args.vcs_opts.valid_vcs(&vcs_dir(&args.workspace_root)?)?;
+        (None, Some(journal))
+    };
+    if let Some(sandbox) = &sandbox {
+        args.package_ids = sandbox.package_ids().clone();
+    }
 
     let mut active_units = IndexMap::new();
-    match fix(&args, &mut active_units) {
-        Ok(()) => Ok(()),
+    let result = match (args.check_flags.feature_matrix(), args.vcs_opts.commit) {
+        (Some(matrix), _) => fix_feature_matrix(
+            &mut args,
+            matrix,
+            &metadata,
+            sandbox.as_ref(),
+            journal.as_mut(),
+            &mut active_units,
+        ),
+        (None, Some(grouping)) => {
+            fix_and_commit(&args, grouping, journal.as_mut(), &mut active_units)
+        }
+        (None, None) => fix(
+            &args,
+            sandbox.as_ref(),
+            journal.as_mut(),
+            None,
+            &mut active_units,
+        )
+        .map(|pass| total_fixes(&pass.fixed)),
+    };
+    let result = result.and_then(|fixes| match (&args.verify, &journal) {
+        (Some(command), Some(journal)) => {
+            verify(&args, command, sandbox.as_ref(), journal).map(|()| fixes)
+        }
+        _ => Ok(fixes),
+    });
+    let fixes = match result {
+        Ok(fixes) => fixes,
+        Err(error) if matches!(error.downcast_ref(), Some(FixitError::Interrupted)) => {
+            if let Some(journal) = journal.filter(|_| sandbox.is_none()) {
+                settle(
+                    &args,
+                    journal,
+                    metadata.workspace_root.as_std_path(),
+                    args.on_interrupt,
+                )?;
+            }
+            return Err(error);
+        }
         Err(error) => {
             for (file, original) in active_units
                 .values()
                 .flat_map(|state| state.snapshots.iter())
             {
-                paths::write(file, &original.original_source)?;
+                paths::write(
+                    resolve_path(sandbox.as_ref(), file),
+                    &original.original_source,
+                )?;
+                args.emit(&Event::FileReverted { file })?;
+            }
+            match journal {
+                // Fixes from units that finished, or were committed, before the error stay.
+                Some(journal) if sandbox.is_none() && !journal.modified_files()?.is_empty() => {
+                    shell::note("kept the fixes made before the error")?;
+                    record_run(journal)?;
+                }
+                Some(journal) => journal.finish()?,
+                None => {}
+            }
+            return Err(error);
+        }
+    };
+    if fixes > 0 && args.applicability() != Applicability::Machine {
+        shell::note(format!(
+            "applied suggestions with `{}` applicability or better",
+            args.applicability()
+        ))?;
+    }
+    if let (Some(sandbox), Some(journal), Some(patch)) = (&sandbox, &journal, &args.sandbox) {
+        hand_back(sandbox, journal, patch.as_deref(), &metadata)?;
+    } else if let Some(journal) = journal {
+        record_run(journal)?;
+    }
+    Ok(())
+}
+
+/// Keeps the run for `--undo`, telling the user its id.
+fn record_run(journal: Journal) -> CargoResult<()> {
+    if let Some(id) = journal.finish_run()? {
+        shell::status(
+            "Recorded",
+            format!("run {id}, undo it with `cargo fixit --undo {id}`"),
+        )?;
+    }
+    Ok(())
+}
+
+/// Where to look for the version control system of the workspace.
+///
+/// Like `cargo fix`, this is the current directory, unless it is outside of the workspace, as
+/// when fixing another workspace through its manifest path.
+fn vcs_dir(workspace_root: &Path) -> CargoResult<PathBuf> {
+    let cwd = env::current_dir()?;
+    Ok(if cwd.starts_with(workspace_root) {
+        cwd
+    } else {
+        workspace_root.to_owned()
+    })
+}
+
+/// Runs the `--verify` command, reverting the files modified by the run if it fails.
+///
+/// With `--bisect`, the files are split until only those whose fixes make the command fail are
+/// left to revert.
+fn verify(
+    args: &FixitArgs,
+    command: &VerifyCommand,
+    sandbox: Option<&Sandbox>,
+    journal: &Journal,
+) -> CargoResult<()> {
+    let files = journal.modified_files()?;
+    if files.is_empty() {
+        return Ok(());
+    }
+    shell::status("Verifying", command)?;
+    if command.run(sandbox)? {
+        return Ok(());
+    }
+
+    let fixed = files
+        .iter()
+        .map(|path| paths::read(path))
+        .collect::<CargoResult<Vec<_>>>()?;
+    let write = |selected: &[usize]| -> CargoResult<()> {
+        for (i, path) in files.iter().enumerate() {
+            if selected.contains(&i) {
+                paths::write(path, &fixed[i])?;
+            } else {
+                journal.restore(path)?;
+            }
+        }
+        Ok(())
+    };
+    let all = (0..files.len()).collect::<Vec<_>>();
+    let mut breaking = all.clone();
+    if args.bisect && 1 < files.len() {
+        shell::status(
+            "Bisecting",
+            format!("the fixes to {}", plural(files.len(), "file", "files")),
+        )?;
+        let mut passes = |selected: &[usize]| -> CargoResult<bool> {
+            trace!("verifying with the fixes to {selected:?}");
+            write(selected)?;
+            command.run(sandbox)
+        };
+        if passes(&[])? {
+            breaking.clear();
+            let mut kept = Vec::new();
+            let (left, right) = all.split_at(all.len() / 2);
+            find_breaking(&mut passes, &mut kept, left, &mut breaking)?;
+            find_breaking(&mut passes, &mut kept, right, &mut breaking)?;
+        } else {
+            shell::warn(format!("`{command}` also fails without the fixes"))?;
+        }
+    }
+    write(
+        &all.iter()
+            .copied()
+            .filter(|i| !breaking.contains(i))
+            .collect::<Vec<_>>(),
+    )?;
+
+    let (root, cwd) = compiler_dirs(args, sandbox)?;
+    let mut reverted = Vec::new();
+    for &i in &breaking {
+        let file = files[i]
+            .strip_prefix(&root)
+            .unwrap_or(files[i])
+            .to_string_lossy();
+        shell::note(format!("reverting `{file}` to its original state"))?;
+        // Relative to the current directory, like the files of the other events.
+        let event_file = files[i].strip_prefix(&cwd).unwrap_or(files[i]);
+        args.emit(&Event::FileReverted {
+            file: &event_file.to_string_lossy(),
+        })?;
+        reverted.push(file.into_owned());
+    }
+    if breaking.len() < files.len() {
+        shell::warn(format!(
+            "reverted the fixes to {} as `{command}` failed with them",
+            plural(reverted.len(), "file", "files")
+        ))?;
+        return Ok(());
+    }
+    Err(FixitError::VerifyFailed {
+        command: command.to_string(),
+        reverted,
+    }
+    .into())
+}
+
+/// Applies the fixes made in `sandbox` to the workspace, or saves them to `patch`.
+///
+/// Files edited in the workspace since the copy was made keep their edits.  If that is not
+/// possible, the fixes are saved as a patch instead.
+fn hand_back(
+    sandbox: &Sandbox,
+    journal: &Journal,
+    patch: Option<&Path>,
+    metadata: &Metadata,
+) -> CargoResult<()> {
+    let mut files = Vec::new();
+    for path in journal.modified_files()? {
+        let name = path
+            .strip_prefix(sandbox.root())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned();
+        files.push(HandBack {
+            original: journal.original(path)?,
+            fixed: paths::read(path)?,
+            target: sandbox.workspace_root().join(&name),
+            name,
+        });
+    }
+
+    if let Some(patch) = patch {
+        return save_patch(&files, patch);
+    }
+    let mut rebased = Vec::new();
+    let mut conflicts = String::new();
+    for file in &files {
+        let current = paths::read(&file.target)?;
+        match rebase(&file.original, &file.fixed, &current) {
+            Some(contents) => rebased.push((file, contents)),
+            None => conflicts.push_str(&format!("  * {}\n", file.name)),
+        }
+    }
+    if !conflicts.is_empty() {
+        let patch = metadata
+            .target_directory
+            .as_std_path()
+            .join("fixit")
+            .join("sandbox.patch");
+        save_patch(&files, &patch)?;
+        anyhow::bail!(
+            "unable to apply the fixes as these files were changed where fixes were made:\n\n\
+            {conflicts}\n\
+            the fixes were saved to `{}`",
+            patch.display()
+        );
+    }
+    // Recorded like any other run, so the fixes can be recovered or undone in the workspace.
+    let mut journal = Journal::open(metadata.target_directory.as_std_path())?;
+    for (file, contents) in rebased {
+        journal.record(&file.target)?;
+        paths::write(&file.target, contents)?;
+    }
+    record_run(journal)
+}
+
+/// A file fixed in a sandbox.
+#[derive(Debug)]
+struct HandBack {
+    /// The path relative to the workspace root
+    name: String,
+    original: String,
+    fixed: String,
+    /// The file in the workspace
+    target: PathBuf,
+}
+
+fn save_patch(files: &[HandBack], patch: &Path) -> CargoResult<()> {
+    let mut contents = String::new();
+    for file in files {
+        let diff = similar::TextDiff::from_lines(&file.original, &file.fixed);
+        contents.push_str(
+            &diff
+                .unified_diff()
+                .header(&format!("a/{}", file.name), &format!("b/{}", file.name))
+                .to_string(),
+        );
+    }
+    if let Some(parent) = patch.parent() {
+        paths::create_dir_all(parent)?;
+    }
+    paths::write(patch, contents)?;
+    shell::status("Saved", format!("fixes as {}", patch.display()))
+}
+
+/// Fixes the code in passes, committing each group of fixes as it is done.
+///
+/// Returns how many fixes were made.
+fn fix_and_commit(
+    args: &FixitArgs,
+    grouping: CommitGrouping,
+    mut journal: Option<&mut Journal>,
+    active_units: &mut IndexMap<UnitId, ActiveState>,
+) -> CargoResult<u32> {
+    let committer = Committer::open(&args.workspace_root)?;
+    if let Some(branch) = &args.vcs_opts.branch {
+        committer.create_branch(branch)?;
+    }
+    let commit = |fixed: &FixedFiles, subject: &str| -> CargoResult<()> {
+        if fixed.is_empty() {
+            return Ok(());
+        }
+        let fixes = total_fixes(fixed);
+        let message = format!(
+            "{subject} ({} in {})",
+            plural(fixes as usize, "fix", "fixes"),
+            plural(fixed.len(), "file", "files")
+        );
+        let files = fixed.keys().map(Path::new).collect::<Vec<_>>();
+        committer.commit(&files, &message)?;
+        shell::status("Committed", message)
+    };
+
+    let mut total = 0;
+    match grouping {
+        CommitGrouping::Single => {
+            let fixed = fix(args, None, journal, None, active_units)?.fixed;
+            total = total_fixes(&fixed);
+            commit(&fixed, "Fix warnings")?;
+        }
+        CommitGrouping::Package => {
+            let fixed = fix(args, None, journal, None, active_units)?.fixed;
+            total = total_fixes(&fixed);
+            let mut packages: IndexMap<String, FixedFiles> = IndexMap::new();
+            for (file, fixed) in fixed {
+                let files = packages.entry(fixed.package_id.clone()).or_default();
+                files.insert(file, fixed);
+            }
+            for (package_id, fixed) in &packages {
+                let name = PackageIdSpec::parse(package_id)?.name().to_owned();
+                commit(fixed, &format!("Fix warnings in {name}"))?;
+            }
+        }
+        CommitGrouping::Lint => {
+            // One pass per lint, then a last pass for anything without a lint name.
+            let mut focus = LintFocus::default();
+            loop {
+                let fixed = fix(
+                    args,
+                    None,
+                    journal.as_deref_mut(),
+                    Some(&mut focus),
+                    active_units,
+                )?
+                .fixed;
+                total += total_fixes(&fixed);
+                let Some(lint) = focus.current.take() else {
+                    commit(&fixed, "Fix warnings")?;
+                    break;
+                };
+                commit(&fixed, &format!("Fix {lint}"))?;
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// How many feature combinations of a package are worth a warning, as each is a separate build.
+const MANY_FEATURE_COMBINATIONS: usize = 32;
+
+/// Fixes each selected package once for every combination of its features, then summarizes what
+/// each combination fixed and left behind.
+///
+/// Returns how many fixes were made.
+fn fix_feature_matrix(
+    args: &mut FixitArgs,
+    matrix: FeatureMatrix,
+    metadata: &Metadata,
+    sandbox: Option<&Sandbox>,
+    mut journal: Option<&mut Journal>,
+    active_units: &mut IndexMap<UnitId, ActiveState>,
+) -> CargoResult<u32> {
+    let primary_packages = PrimaryPackages::from_metadata(
+        metadata,
+        &args.check_flags,
+        args.config.exclude_packages(),
+    )?;
+    let check_flags = args.check_flags.clone();
+    let mut summary = Vec::new();
+    let mut total = 0;
+    for package in metadata.workspace_packages() {
+        if !primary_packages.contains(&package.id.repr) {
+            continue;
+        }
+        let combinations = matrix.combinations(&package.features);
+        if combinations.len() > MANY_FEATURE_COMBINATIONS {
+            shell::warn(format!(
+                "fixing `{}` with {} combinations of features",
+                package.name,
+                combinations.len()
+            ))?;
+            if let FeatureMatrix::Powerset { .. } = matrix {
+                shell::note("pass a lower `--depth` to combine fewer features at a time")?;
             }
-            Err(error)
         }
+        for features in combinations {
+            shell::status("Fixing", format!("{} with {features}", package.name))?;
+            args.check_flags = check_flags.for_features(&package.name, &features);
+            let pass = fix(args, sandbox, journal.as_deref_mut(), None, active_units);
+            args.check_flags = check_flags.clone();
+            summary.push((format!("{} with {features}", package.name), pass?));
+        }
+    }
+
+    for (combination, pass) in summary {
+        let fixes = total_fixes(&pass.fixed);
+        total += fixes;
+        shell::status(
+            "Summary",
+            format!(
+                "{combination}: {}, {} remaining",
+                plural(fixes as usize, "fix", "fixes"),
+                plural(pass.remaining, "warning", "warnings")
+            ),
+        )?;
+    }
+    Ok(total)
+}
+
+fn total_fixes(fixed: &FixedFiles) -> u32 {
+    fixed.values().map(|fixed| fixed.fixes).sum()
+}
+
+fn plural(count: usize, one: &str, many: &str) -> String {
+    if count == 1 {
+        format!("{count} {one}")
+    } else {
+        format!("{count} {many}")
+    }
+}
+
+/// Restricts each run to the fixes of a single lint.
+#[derive(Debug, Default)]
+struct LintFocus {
+    current: Option<String>,
+    done: BTreeSet<String>,
+}
+
+impl LintFocus {
+    /// Picks the next lint that has fixes, or `None` once every lint had its turn.
+    fn choose(&mut self, filter: &FixFilter, messages: &[CheckOutput]) -> Option<String> {
+        let lints = messages
+            .iter()
+            .filter_map(|message| match message {
+                CheckOutput::Message(Message {
+                    build_unit,
+                    message: MessageDiagnostic { diagnostic, .. },
+                }) => filter.fixable_lint(&build_unit.package_id, diagnostic),
+                CheckOutput::Artifact(_) => None,
+            })
+            .collect::<BTreeSet<_>>();
+        self.current = lints
+            .into_iter()
+            .find(|lint| !self.done.contains(*lint))
+            .map(ToOwned::to_owned);
+        self.done.extend(self.current.clone());
+        self.current.clone()
+    }
+}
+
+fn recover(args: &FixitArgs, recovery: Recovery) -> CargoResult<()> {
+    let metadata = package_metadata(&args.check_flags, None)?;
+    let Some(journal) = Journal::interrupted(metadata.target_directory.as_std_path())? else {
+        shell::note("no interrupted `cargo fixit` run to recover")?;
+        return Ok(());
+    };
+    settle(
+        args,
+        journal,
+        metadata.workspace_root.as_std_path(),
+        recovery,
+    )
+}
+
+fn undo(args: &FixitArgs, run_id: Option<&str>) -> CargoResult<()> {
+    let metadata = package_metadata(&args.check_flags, None)?;
+    let run = Run::find(metadata.target_directory.as_std_path(), run_id)?;
+    let relative = |path: &Path| {
+        path.strip_prefix(&metadata.workspace_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    let files = run.undone()?;
+    let diverged = files
+        .iter()
+        .filter(|(_, undone)| undone.is_none())
+        .map(|(path, _)| format!("  * {}", relative(path)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !diverged.is_empty() {
+        anyhow::bail!(
+            "unable to undo run `{}` as these files were changed where it made fixes:\n\n\
+            {}",
+            run.id(),
+            diverged
+        );
+    }
+    for (path, undone) in &files {
+        let Some(undone) = undone else { continue };
+        let file = relative(path);
+        shell::note(format!("undoing the fixes in `{file}`"))?;
+        paths::write(path, undone)?;
+        args.emit(&Event::FileReverted { file: &file })?;
     }
+    run.finish()
 }
 
-fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> CargoResult<()> {
+/// Restores or keeps every file modified by a run that did not finish, reporting each one.
+fn settle(
+    args: &FixitArgs,
+    journal: Journal,
+    workspace_root: &Path,
+    recovery: Recovery,
+) -> CargoResult<()> {
+    for path in journal.modified_files()? {
+        let file = path
+            .strip_prefix(workspace_root)
+            .unwrap_or(path)
+            .to_string_lossy();
+        match recovery {
+            Recovery::Restore => {
+                shell::note(format!("reverting `{file}` to its original state"))?;
+                journal.restore(path)?;
+                args.emit(&Event::FileReverted { file: &file })?;
+            }
+            Recovery::Finalize => {
+                shell::note(format!("keeping the fixes in `{file}`"))?;
+            }
+        }
+    }
+    journal.finish()
+}
+
+fn fix(
+    args: &FixitArgs,
+    sandbox: Option<&Sandbox>,
+    mut journal: Option<&mut Journal>,
+    mut focus: Option<&mut LintFocus>,
+    active_units: &mut IndexMap<UnitId, ActiveState>,
+) -> CargoResult<Pass> {
     let max_iterations: usize = env::var("CARGO_FIX_MAX_RETRIES")
         .ok()
         .and_then(|i| i.parse().ok())
+        .or(args.config.max_iterations())
         .unwrap_or(4);
-    let package_metadata = package_metadata(&args.check_flags)?;
-    let primary_packages = PrimaryPackages::from_metadata(&package_metadata, &args.check_flags)?;
+    let package_metadata = package_metadata(&args.check_flags, sandbox)?;
+    let primary_packages = PrimaryPackages::from_metadata(
+        &package_metadata,
+        &args.check_flags,
+        args.config.exclude_packages(),
+    )?;
+    let migrations = if args.edition {
+        edition_migrations(&package_metadata, &primary_packages)?
+    } else {
+        Vec::new()
+    };
+    let package_edition_lints =
+        edition_lints(args, &package_metadata, &primary_packages, &migrations);
+    // The lints can only be enabled for all packages, the others' diagnostics being noise.
+    let mut edition_lints = BTreeMap::new();
+    for (lint, forced) in package_edition_lints.values().flatten() {
+        *edition_lints.entry(lint.clone()).or_insert(false) |= *forced;
+    }
+    let migration_lints = LintOpts {
+        lints: edition_lints.keys().cloned().collect(),
+        skip_lints: Vec::new(),
+    };
+    let mut lint_opts = args.lint_opts.clone();
+    // Only migrate, leaving other warnings for the user to fix separately.
+    let migrate_only = lint_opts.lints.is_empty() && (args.edition || args.edition_idioms);
+    if migrate_only {
+        lint_opts.lints.clone_from(&migration_lints.lints);
+    }
+    // An empty list selects every lint, so none are when there is nothing to migrate.
+    let fixes_nothing = migrate_only && lint_opts.lints.is_empty();
+    let to_filter = |lint_opts: LintOpts| {
+        if fixes_nothing {
+            Ok(LintFilter::nothing())
+        } else {
+            lint_opts.to_filter(args.clippy())
+        }
+    };
+    let lint_flags = edition_lints
+        .iter()
+        .map(|(lint, forced)| {
+            if *forced {
+                format!("--force-warn={lint}")
+            } else {
+                format!("-W{lint}")
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut dir_rules = Vec::new();
+    for (dir, lint_opts, applicability) in args.config.dir_overrides(&lint_opts, args.applicability)
+    {
+        let rules = Rules {
+            lint_filter: to_filter(lint_opts)?,
+            applicability: applicability.unwrap_or_else(|| args.applicability()),
+        };
+        dir_rules.push((dir.to_owned(), rules));
+    }
+    let (root, cwd) = compiler_dirs(args, sandbox)?;
+    let mut filter = FixFilter {
+        primary_packages,
+        speculative: args.speculative,
+        rules: Rules {
+            lint_filter: to_filter(args.config.lint_opts(&lint_opts))?,
+            applicability: args.applicability(),
+        },
+        dir_rules,
+        root,
+        cwd,
+        excluded_paths: args.config.excluded_paths(),
+        migration_lints: migration_lints
+            .lints
+            .iter()
+            .map(|lint| {
+                let group = LintOpts {
+                    lints: vec![lint.clone()],
+                    skip_lints: Vec::new(),
+                };
+                Ok((lint.clone(), group.to_filter(args.clippy())?))
+            })
+            .collect::<CargoResult<_>>()?,
+        package_migrations: package_edition_lints
+            .into_iter()
+            .map(|(package_id, lints)| (package_id, lints.into_keys().collect()))
+            .collect(),
+        focus: None,
+        changed_lines: ChangedLines::from_opts(
+            &args.vcs_opts,
+            sandbox
+                .map(Sandbox::workspace_root)
+                .unwrap_or(package_metadata.workspace_root.as_std_path()),
+        )?,
+        dirty_files: args.vcs_opts.dirty_files(&args.workspace_root)?,
+    };
+    let unit_variants = UnitVariants::new(&package_metadata, &args.check_flags);
     let mut plan = if args.dangerous_parallel_fixes {
-        UnitGraph::flat(&package_metadata)
+        UnitGraph::flat(&package_metadata, &unit_variants)
     } else {
-        UnitGraph::new(&package_metadata)
+        UnitGraph::new(&package_metadata, &unit_variants)
     };
     trace!("plan `{plan:#?}`");
 
@@ -130,31 +1061,69 @@ fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> Ca
     let mut seen = BTreeSet::new();
     let mut first = true;
     let mut claimed_files: HashMap<same_file::Handle, UnitId> = HashMap::new();
+    let mut fixed_files = FixedFiles::new();
+    let mut remaining = 0;
+    let mut risky_files = IndexSet::new();
+    let mut reviewer = args.interactive.then(Reviewer::new);
+    let mut round = Round::default();
+    let mut broken = HashSet::new();
+    let mut skipped = IndexSet::new();
     loop {
+        interrupt::check()?;
         trace!("check ({active_units:?})");
-        let (mut messages, exit_code) = check(args, &mut lint_cap)?;
+        let (mut messages, exit_code) = check(args, sandbox, &lint_flags, &mut lint_cap)?;
         messages.sort_unstable_by_key(|m| m.build_unit().cloned());
         print_built(args, &messages)?;
 
         if messages.is_empty() && exit_code != Some(0) {
-            let mut command = args.to_command();
+            interrupt::check()?;
+            let mut command = args.to_command(sandbox);
             command.status()?;
-            anyhow::bail!("could not compile");
+            return Err(FixitError::CargoTerminated { exit_code }.into());
+        } else if !args.broken_code
+            && (exit_code != Some(0)
+                || round
+                    .speculation
+                    .as_ref()
+                    .is_some_and(|speculation| speculation.adds_warnings(&messages)))
+            && !round.fixes.is_empty()
+            && bisect_round(
+                args,
+                sandbox,
+                &lint_flags,
+                &mut lint_cap,
+                &mut round,
+                active_units,
+                &mut broken,
+            )?
+        {
+            continue;
         } else if !args.broken_code && exit_code != Some(0) {
             let mut out = String::new();
+            let rendered = messages
+                .into_iter()
+                .filter_map(|e| match e {
+                    CheckOutput::Message(m) => m.message.diagnostic.rendered,
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
 
-            if !active_units.is_empty() {
+            // Bisecting may have dropped every fix, leaving nothing to blame.
+            let still_applied = active_units
+                .values()
+                .any(|state| !state.snapshots.is_empty());
+            let error = if still_applied {
                 out.push_str(
                     "failed to automatically apply fixes suggested by rustc\n\n\
                     after fixes were automatically applied the \
                     compiler reported errors within these files:\n\n",
                 );
 
+                let mut reverted = Vec::new();
                 for (
                     file,
                     File {
-                        fixes: _,
-                        original_source,
+                        original_source, ..
                     },
                 ) in active_units
                     .values()
@@ -162,29 +1131,24 @@ fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> Ca
                 {
                     out.push_str(&format!("  * {file}\n"));
                     shell::note(format!("reverting `{file}` to its original state"))?;
-                    paths::write(file, original_source)?;
+                    paths::write(resolve_path(sandbox, file), original_source)?;
+                    args.emit(&Event::FileReverted { file })?;
+                    reverted.push(file.clone());
                 }
                 active_units.clear();
                 out.push('\n');
 
-                out.push_str(&gen_please_report_this_bug_text(args.clippy));
+                out.push_str(&gen_please_report_this_bug_text(args.clippy()));
 
-                let mut errors = messages
-                    .into_iter()
-                    .filter_map(|e| match e {
-                        CheckOutput::Message(m) => m.message.diagnostic.rendered,
-                        _ => None,
-                    })
-                    .peekable();
-                if errors.peek().is_some() {
+                if !rendered.is_empty() {
                     out.push_str("The errors reported are:\n");
                 }
 
-                for e in errors {
+                for e in &rendered {
                     out.push_str(&format!("{}\n\n", e.trim_end()));
                 }
 
-                let (messages, _) = check(args, &mut lint_cap)?;
+                let (messages, _) = check(args, sandbox, &lint_flags, &mut lint_cap)?;
                 print_built(args, &messages)?;
                 let mut errors = messages
                     .into_iter()
@@ -203,19 +1167,24 @@ fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> Ca
                 }
 
                 shell::warn(out)?;
+                FixitError::FixesBrokeBuild {
+                    reverted,
+                    errors: rendered,
+                }
             } else {
-                for e in messages.into_iter().filter_map(|e| match e {
-                    CheckOutput::Message(m) => m.message.diagnostic.rendered,
-                    _ => None,
-                }) {
+                for e in &rendered {
                     shell::print_ansi_stderr(format!("{}\n\n", e.trim_end()).as_bytes())?;
                 }
-            }
+                FixitError::CompileFailed { errors: rendered }
+            };
 
             shell::note("try using `--broken-code` to fix errors")?;
-            anyhow::bail!("could not compile");
+            return Err(error.into());
         }
         if first {
+            if let Some(focus) = focus.as_deref_mut() {
+                filter.focus = focus.choose(&filter, &messages);
+            }
             first = false;
             let mut errors = IndexMap::new();
             for message in &messages {
@@ -225,9 +1194,14 @@ fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> Ca
                         message: MessageDiagnostic { diagnostic, .. },
                     }) => {
                         let unit_id = UnitId::from_message(build_unit);
-                        if let Some(rendered) = diagnostic.rendered.clone() {
+                        if filter.is_migration_noise(&build_unit.package_id, diagnostic)
+                            || filter.is_deferred(diagnostic)
+                        {
+                            continue;
+                        }
+                        if let Some(error) = UnfixedDiagnostic::new(diagnostic.clone()) {
                             let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
-                            errors.insert(rendered);
+                            errors.insert(error);
                         }
                     }
                     CheckOutput::Artifact(a) => {
@@ -236,7 +1210,7 @@ fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> Ca
                         if !is_local(package_id) || !plan.dependencies.contains_key(&unit_id) {
                             for error in errors.get(&unit_id).into_iter().flatten() {
                                 shell::print_ansi_stderr(
-                                    format!("{}\n\n", error.trim_end()).as_bytes(),
+                                    format!("{}\n\n", error.rendered.trim_end()).as_bytes(),
                                 )?;
                             }
                             if !a.fresh && seen.insert(package_id.to_owned()) {
@@ -248,18 +1222,37 @@ fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> Ca
             }
         }
 
+        let mut warnings = if args.speculative {
+            unit_warnings(&messages)
+        } else {
+            HashMap::new()
+        };
         let observed_packages: HashSet<String> = messages
             .iter()
             .filter_map(CheckOutput::build_unit)
             .map(|unit| unit.package_id.clone())
             .collect();
-        let (mut errors, suggestions) = collect_diagnostics(
+        let (mut errors, mut suggestions) = collect_diagnostics(
             messages.into_iter(),
             &plan.finished,
-            &primary_packages,
+            &filter,
+            sandbox,
             active_units,
             max_iterations,
+            &mut skipped,
         );
+        if reviewer.is_some() || !broken.is_empty() {
+            discard_rejected(
+                reviewer.as_ref(),
+                &broken,
+                sandbox,
+                &mut suggestions,
+                &mut errors,
+            );
+        }
+        if args.speculative {
+            defer_speculative(&mut suggestions);
+        }
 
         let mut finishing = true;
         while finishing {
@@ -268,8 +1261,30 @@ fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> Ca
                 if suggestions.contains_key(unit_id) {
                     continue;
                 }
+                if args.fmt {
+                    format_unit(&package_metadata, unit_id, active_units, sandbox)?;
+                }
                 let errors = errors.shift_remove(unit_id);
-                finish_unit(unit_id, active_units, errors.as_ref())?;
+                remaining += errors.as_ref().map(IndexSet::len).unwrap_or(0);
+                finish_unit(args, unit_id, active_units, errors.as_ref())?;
+                if let Some(state) = active_units.get(unit_id) {
+                    for (name, file) in &state.snapshots {
+                        fixed_files
+                            .entry(name.clone())
+                            .or_insert_with(|| Fixed {
+                                package_id: unit_id.package_id().to_owned(),
+                                fixes: 0,
+                            })
+                            .fixes += file.fixes;
+                    }
+                    risky_files.extend(
+                        state
+                            .snapshots
+                            .iter()
+                            .filter(|(_, file)| 0 < file.risky_fixes)
+                            .map(|(name, _)| name.clone()),
+                    );
+                }
                 finished.insert(unit_id.clone());
             }
             active_units.retain(|k, _v| !finished.contains(k));
@@ -283,6 +1298,10 @@ fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> Ca
                 if observed_packages.contains(package_id) && seen.insert(package_id.to_owned()) {
                     shell::status("Checking", format_package_id(package_id)?)?;
                 }
+                args.emit(&Event::UnitScheduled {
+                    package_id,
+                    target_kind: unit_id.target_kind().as_str(),
+                })?;
                 active_units.insert(unit_id, Default::default());
             }
         }
@@ -291,12 +1310,25 @@ fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> Ca
             break;
         }
 
+        round = Round::default();
         'units: for (unit_id, state) in active_units.iter_mut() {
             let unit_suggestions = suggestions
                 .get(unit_id)
                 .expect("finished all active_units without suggestions");
-            for path in state.snapshots.keys().chain(unit_suggestions.keys()) {
-                let Ok(handle) = same_file::Handle::from_path(path) else {
+            let speculative = unit_suggestions
+                .values()
+                .flatten()
+                .any(|fix| fix.speculative);
+            if speculative && round.speculation.is_some() {
+                trace!("deferring `{unit_id:?}` while another unit speculates");
+                continue;
+            }
+            let touched = unit_suggestions
+                .values()
+                .flatten()
+                .flat_map(|fix| touched_files(&fix.suggestion));
+            for path in state.snapshots.keys().map(String::as_str).chain(touched) {
+                let Ok(handle) = same_file::Handle::from_path(resolve_path(sandbox, path)) else {
                     continue;
                 };
                 match claimed_files.entry(handle) {
@@ -313,12 +1345,364 @@ fn fix(args: &FixitArgs, active_units: &mut IndexMap<UnitId, ActiveState>) -> Ca
                     }
                 }
             }
+            if speculative {
+                round.speculation = Some(Speculation {
+                    unit_id: unit_id.clone(),
+                    warnings: warnings.remove(unit_id).unwrap_or_default(),
+                });
+            }
+            if let Some(journal) = journal.as_deref_mut() {
+                for path in unit_suggestions
+                    .values()
+                    .flatten()
+                    .flat_map(|fix| touched_files(&fix.suggestion))
+                {
+                    journal.record(&resolve_path(sandbox, path))?;
+                }
+            }
             trace!("fixing `{unit_id:?}` {state:?}");
             state.iterations += 1;
-            let _made_changes = fix_suggestions(unit_suggestions, state)?;
+            let _made_changes = fix_suggestions(
+                args,
+                unit_id,
+                unit_suggestions,
+                sandbox,
+                state,
+                reviewer.as_mut(),
+                &mut round,
+            )?;
         }
     }
-    Ok(())
+
+    let workspace_manifest = package_metadata.workspace_root.join("Cargo.toml");
+    let mut migrated = IndexSet::new();
+    // With a lint in focus, the migration waits for the last run so it is committed with the rest.
+    let migrations = if filter.focus.is_none() {
+        migrations
+    } else {
+        Vec::new()
+    };
+    let relative = |path: &Path| {
+        path.strip_prefix(&package_metadata.workspace_root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    for migration in migrations {
+        if let Some(manifest) = [&migration.manifest_path, workspace_manifest.as_std_path()]
+            .into_iter()
+            .find(|manifest| filter.is_dirty(&original_path(sandbox, &manifest.to_string_lossy())))
+        {
+            shell::warn(format!(
+                "skipped migrating `{}` as `{}` has uncommitted changes",
+                relative(&migration.manifest_path),
+                relative(manifest)
+            ))?;
+            continue;
+        }
+        if let Some(journal) = journal.as_deref_mut() {
+            journal.record(&migration.manifest_path)?;
+            journal.record(workspace_manifest.as_std_path())?;
+        }
+        let manifest = edition::set_edition(
+            &migration.manifest_path,
+            workspace_manifest.as_std_path(),
+            migration.to,
+        )?;
+        if migrated.insert(manifest.to_owned()) {
+            shell::status(
+                "Migrating",
+                format!(
+                    "{} from {} edition to {}",
+                    relative(manifest),
+                    migration.from.as_str(),
+                    migration.to.as_str()
+                ),
+            )?;
+        }
+        // Relative to the current directory, like the paths of the diagnostics.
+        let file = manifest
+            .strip_prefix(&filter.cwd)
+            .unwrap_or(manifest)
+            .to_string_lossy()
+            .into_owned();
+        fixed_files.entry(file).or_insert(Fixed {
+            package_id: migration.package_id,
+            fixes: 0,
+        });
+    }
+
+    for file in &skipped {
+        shell::warn(format!(
+            "skipped fixes to `{file}` as it has uncommitted changes"
+        ))?;
+        args.emit(&Event::FileSkipped { file })?;
+    }
+    for file in risky_files {
+        shell::warn(format!(
+            "`{file}` has fixes that are not machine-applicable and should be reviewed"
+        ))?;
+    }
+    if let Some(sandbox) = sandbox.filter(|_| args.dry_run) {
+        for file in fixed_files.keys() {
+            let original = paths::read(&sandbox.original_path(file))?;
+            let fixed = paths::read(&sandbox.path(file))?;
+            // Files outside the current directory are named from the workspace root rather than
+            // by where the copy is.
+            let name = Path::new(file)
+                .strip_prefix(sandbox.root())
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|_| file.clone());
+            // Keep stdout to the JSON events.
+            if args.message_format == MessageFormat::Json {
+                shell::diff_stderr(&name, &original, &fixed)?;
+            } else {
+                shell::diff(&name, &original, &fixed)?;
+            }
+        }
+    }
+    Ok(Pass {
+        fixed: fixed_files,
+        remaining,
+    })
+}
+
+/// A package to move to the next edition once its fixes are applied.
+#[derive(Debug)]
+struct Migration {
+    package_id: String,
+    manifest_path: PathBuf,
+    from: Edition,
+    to: Edition,
+}
+
+fn edition_migrations(
+    metadata: &Metadata,
+    primary_packages: &PrimaryPackages,
+) -> CargoResult<Vec<Migration>> {
+    let mut migrations = Vec::new();
+    for package in metadata.workspace_packages() {
+        if !primary_packages.contains(&package.id.repr) {
+            continue;
+        }
+        match edition::next(package.edition) {
+            Some(to) => migrations.push(Migration {
+                package_id: package.id.repr.clone(),
+                manifest_path: package.manifest_path.clone().into_std_path_buf(),
+                from: package.edition,
+                to,
+            }),
+            None => shell::warn(format!(
+                "`{}` is already on the latest edition ({}), unable to migrate further",
+                package.name,
+                package.edition.as_str()
+            ))?,
+        }
+    }
+
+    // The edition of `[workspace.package]` can only move for all the packages sharing it.
+    let mut inheriting = Vec::new();
+    for package in metadata.workspace_packages() {
+        if edition::inherits_edition(package.manifest_path.as_std_path())? {
+            inheriting.push(package);
+        }
+    }
+    let migrates = |package: &cargo_metadata::Package| {
+        migrations
+            .iter()
+            .any(|migration| migration.package_id == package.id.repr)
+    };
+    if let Some(package) = inheriting.iter().find(|package| migrates(package)) {
+        let left_out = inheriting
+            .iter()
+            .filter(|package| !migrates(package))
+            .map(|package| format!("`{}`", package.name))
+            .collect::<Vec<_>>();
+        if !left_out.is_empty() {
+            anyhow::bail!(
+                "unable to migrate `{}` as it shares the workspace's edition with {}\n\
+                select them as well, or set the edition of `{}` in its own manifest",
+                package.name,
+                left_out.join(", "),
+                package.name
+            );
+        }
+    }
+    Ok(migrations)
+}
+
+/// Lint groups that perform edition migrations for each package, and whether they override
+/// `allow`s.
+fn edition_lints(
+    args: &FixitArgs,
+    metadata: &Metadata,
+    primary_packages: &PrimaryPackages,
+    migrations: &[Migration],
+) -> BTreeMap<String, BTreeMap<String, bool>> {
+    let mut lints: BTreeMap<String, BTreeMap<String, bool>> = BTreeMap::new();
+    for migration in migrations {
+        if let Some(lint) = edition::compatibility_lint(migration.to) {
+            // Edition lints must apply even where the user allowed them.
+            lints
+                .entry(migration.package_id.clone())
+                .or_default()
+                .insert(lint.to_owned(), true);
+        }
+    }
+    if args.edition_idioms {
+        for package in metadata.workspace_packages() {
+            if !primary_packages.contains(&package.id.repr) {
+                continue;
+            }
+            if let Some(lint) = edition::idioms_lint(package.edition) {
+                lints
+                    .entry(package.id.repr.clone())
+                    .or_default()
+                    .entry(lint.to_owned())
+                    .or_insert(false);
+            }
+        }
+    }
+    lints
+}
+
+/// Resolves a path reported by the compiler to the file that fixes should be written to.
+fn resolve_path(sandbox: Option<&Sandbox>, file: &str) -> PathBuf {
+    match sandbox {
+        Some(sandbox) => sandbox.path(file),
+        None => PathBuf::from(file),
+    }
+}
+
+/// Resolves a path reported by the compiler to the file in the user's workspace.
+fn original_path(sandbox: Option<&Sandbox>, file: &str) -> PathBuf {
+    match sandbox {
+        Some(sandbox) => sandbox.original_path(&sandbox.path(file).to_string_lossy()),
+        None => PathBuf::from(file),
+    }
+}
+
+/// Decides which diagnostics are eligible to have their suggestions applied.
+#[derive(Debug)]
+struct FixFilter {
+    primary_packages: PrimaryPackages,
+    /// Whether `maybe-incorrect` suggestions are tried with `--speculative`
+    speculative: bool,
+    rules: Rules,
+    /// Directories whose `fixit.toml` overrides the rules, deepest first
+    dir_rules: Vec<(PathBuf, Rules)>,
+    /// The root of the workspace being compiled
+    root: PathBuf,
+    /// The directory paths reported by the compiler are relative to
+    cwd: PathBuf,
+    excluded_paths: ExcludedPaths,
+    /// Lint groups enabled by us to migrate editions
+    migration_lints: BTreeMap<String, LintFilter>,
+    /// The lint groups that migrate each package
+    package_migrations: HashMap<String, BTreeSet<String>>,
+    /// The only lint to fix in this run
+    focus: Option<String>,
+    /// The lines fixes are limited to, with `--since` or `--staged`
+    changed_lines: Option<ChangedLines>,
+    /// Files with uncommitted changes that must not be written to, with `--skip-dirty`
+    dirty_files: Option<HashSet<PathBuf>>,
+}
+
+/// The lints to fix, and how confident the compiler must be in their fixes.
+#[derive(Debug)]
+struct Rules {
+    lint_filter: LintFilter,
+    applicability: Applicability,
+}
+
+impl FixFilter {
+    /// The rules for a diagnostic, which depend on the directory of its file.
+    fn rules(&self, diagnostic: &Diagnostic) -> &Rules {
+        let file = diagnostic
+            .spans
+            .iter()
+            .find(|span| span.is_primary)
+            .and_then(|span| self.workspace_path(&span.file_name));
+        file.and_then(|file| self.dir_rules.iter().find(|(dir, _)| file.starts_with(dir)))
+            .map(|(_, rules)| rules)
+            .unwrap_or(&self.rules)
+    }
+
+    /// Whether `fixit.toml` excludes a file reported by the compiler from being fixed.
+    fn is_excluded(&self, file: &str) -> bool {
+        self.workspace_path(file)
+            .is_some_and(|file| self.excluded_paths.contains(&file))
+    }
+
+    /// A path reported by the compiler, relative to the root of the workspace.
+    fn workspace_path(&self, file: &str) -> Option<PathBuf> {
+        let path = self.cwd.join(file);
+        path.strip_prefix(&self.root).ok().map(ToOwned::to_owned)
+    }
+
+    /// Whether a suggestion edits code outside of the lines being worked on.
+    fn is_unchanged(&self, sandbox: Option<&Sandbox>, suggestion: &Suggestion) -> bool {
+        let Some(changed_lines) = &self.changed_lines else {
+            return false;
+        };
+        !suggestion
+            .solutions
+            .iter()
+            .flat_map(|solution| &solution.replacements)
+            .any(|replacement| {
+                let snippet = &replacement.snippet;
+                changed_lines.contains(
+                    &original_path(sandbox, &snippet.file_name),
+                    snippet.line_range.start.line..=snippet.line_range.end.line,
+                )
+            })
+    }
+
+    /// Whether `path` has uncommitted changes that fixes must not overwrite.
+    fn is_dirty(&self, path: &Path) -> bool {
+        self.dirty_files.as_ref().is_some_and(|dirty_files| {
+            std::path::absolute(path).is_ok_and(|path| dirty_files.contains(&path))
+        })
+    }
+
+    /// Whether a diagnostic is left for a later run focused on its lint.
+    fn is_deferred(&self, diagnostic: &Diagnostic) -> bool {
+        self.focus.as_deref().is_some_and(|focus| {
+            diagnostic.code.as_ref().map(|code| code.code.as_str()) != Some(focus)
+        })
+    }
+
+    /// The lint of a diagnostic that has suggestions to apply.
+    fn fixable_lint<'d>(&self, package_id: &str, diagnostic: &'d Diagnostic) -> Option<&'d str> {
+        let code = diagnostic.code.as_ref()?.code.as_str();
+        let rules = self.rules(diagnostic);
+        let fixable = self.primary_packages.contains(package_id)
+            && rules.lint_filter.is_selected(Some(code))
+            && !self.is_migration_noise(package_id, diagnostic)
+            && rules
+                .applicability
+                .restrict(&mut diagnostic.clone())
+                .is_some();
+        fixable.then_some(code)
+    }
+
+    /// Whether a diagnostic only exists because we enabled a migration lint for every package.
+    fn is_migration_noise(&self, package_id: &str, diagnostic: &Diagnostic) -> bool {
+        let Some(code) = diagnostic.code.as_ref().map(|code| code.code.as_str()) else {
+            return false;
+        };
+        let mut groups = self
+            .migration_lints
+            .iter()
+            .filter(|(_, lints)| lints.is_selected(Some(code)))
+            .map(|(group, _)| group)
+            .peekable();
+        if groups.peek().is_none() {
+            return false;
+        }
+        let own = self.package_migrations.get(package_id);
+        !groups.any(|group| own.is_some_and(|own| own.contains(group)))
+    }
 }
 
 /// Packages that Cargo treats as primary for the current invocation.
@@ -329,18 +1713,29 @@ struct PrimaryPackages {
 
 impl PrimaryPackages {
     /// Reconstructs Cargo's primary package set from its package and target selectors.
-    fn from_metadata(metadata: &Metadata, flags: &CheckFlags) -> CargoResult<Self> {
+    ///
+    /// Packages excluded by the configuration are left out, unless selected by name.
+    fn from_metadata(
+        metadata: &Metadata,
+        flags: &CheckFlags,
+        configured_exclude: &[String],
+    ) -> CargoResult<Self> {
+        let configured_exclude = PackageSpecMatcher::new(configured_exclude)?;
         let mut package_ids = match flags.package_selection() {
-            PackageSelection::Default => metadata
-                .workspace_default_members
-                .iter()
-                .map(|package_id| package_id.repr.clone())
-                .collect(),
+            PackageSelection::Default => {
+                let mut package_ids = HashSet::new();
+                for package in metadata.workspace_default_packages() {
+                    if !configured_exclude.matches(package)? {
+                        package_ids.insert(package.id.repr.clone());
+                    }
+                }
+                package_ids
+            }
             PackageSelection::Workspace { exclude } => {
                 let matcher = PackageSpecMatcher::new(exclude)?;
                 let mut package_ids = HashSet::new();
                 for package in metadata.workspace_packages() {
-                    if !matcher.matches(package)? {
+                    if !matcher.matches(package)? && !configured_exclude.matches(package)? {
                         package_ids.insert(package.id.repr.clone());
                     }
                 }
@@ -436,52 +1831,161 @@ fn package_id_matches(spec: &PackageIdSpec, package_id: &PackageIdSpec) -> bool
 }
 
 /// Loads unresolved package metadata once and reuses it for selection and ordering.
-fn package_metadata(flags: &CheckFlags) -> CargoResult<Metadata> {
+fn package_metadata(flags: &CheckFlags, sandbox: Option<&Sandbox>) -> CargoResult<Metadata> {
     let mut command = MetadataCommand::new();
     command.no_deps();
-    command.other_options(flags.to_metadata_flags());
-    let metadata = command.exec().context("failed to run `cargo metadata`")?;
-    Ok(metadata)
+    let mut options = flags.to_metadata_flags();
+    if let Some(sandbox) = sandbox {
+        command.current_dir(sandbox.cwd());
+        options.extend(sandbox.config_args());
+    }
+    command.other_options(options);
+    command
+        .exec()
+        .map_err(|error| FixitError::Metadata(error).into())
 }
 
 fn finish_unit(
+    args: &FixitArgs,
     unit_id: &UnitId,
     active_units: &IndexMap<UnitId, ActiveState>,
-    errors: Option<&IndexSet<String>>,
+    errors: Option<&IndexSet<UnfixedDiagnostic>>,
 ) -> CargoResult<()> {
     trace!("finishing build unit `{unit_id:?}`");
+    let package_id = unit_id.package_id();
+    let target_kind = unit_id.target_kind().as_str();
+    let mut files = Vec::new();
     if let Some(state) = active_units.get(unit_id) {
         for (name, file) in &state.snapshots {
             shell::fixed(name, file.fixes)?;
+            files.push(FixedFile {
+                file: name,
+                fixes: file.fixes,
+            });
         }
     }
+    args.emit(&Event::UnitFinished {
+        package_id,
+        target_kind,
+        files,
+    })?;
 
     for error in errors.into_iter().flatten() {
-        shell::print_ansi_stderr(format!("{}\n\n", error.trim_end()).as_bytes())?;
+        shell::print_ansi_stderr(format!("{}\n\n", error.rendered.trim_end()).as_bytes())?;
+        args.emit(&Event::DiagnosticRemaining {
+            package_id,
+            target_kind,
+            code: error.code.as_deref(),
+            message: &error.message,
+            rendered: &anstream::adapter::strip_str(&error.rendered).to_string(),
+        })?;
     }
 
     Ok(())
 }
 
-fn check(args: &FixitArgs, lint_cap: &mut bool) -> CargoResult<(Vec<CheckOutput>, Option<i32>)> {
-    let mut command = args.to_command();
+/// Runs rustfmt on the files a unit fixed, with the edition of its package.
+///
+/// A failure leaves the unit active, so its files are reverted along with the rest.
+fn format_unit(
+    metadata: &Metadata,
+    unit_id: &UnitId,
+    active_units: &IndexMap<UnitId, ActiveState>,
+    sandbox: Option<&Sandbox>,
+) -> CargoResult<()> {
+    let Some(state) = active_units.get(unit_id) else {
+        return Ok(());
+    };
+    let edition = metadata
+        .packages
+        .iter()
+        .find(|package| package.id.repr == unit_id.package_id())
+        .map(|package| package.edition)
+        .unwrap_or_default();
+    for file in state.snapshots.keys() {
+        rustfmt::format(&resolve_path(sandbox, file), edition.as_str())?;
+    }
+    Ok(())
+}
+
+fn check(
+    args: &FixitArgs,
+    sandbox: Option<&Sandbox>,
+    lint_flags: &[String],
+    lint_cap: &mut bool,
+) -> CargoResult<(Vec<CheckOutput>, Option<i32>)> {
+    let mut command = args.to_command(sandbox);
     command
         .args(["--message-format", "json-diagnostic-rendered-ansi"])
         .stderr(Stdio::piped())
         .stdout(Stdio::piped());
-    if *lint_cap {
-        cap_lints(&mut command);
-    }
-    let output = command.output()?;
+    add_rustflags(&mut command, lint_flags, *lint_cap);
+    let output = interrupt::output(&mut command)?;
     let mut output = to_check_output(output);
 
     if output.1 != Some(0) && !*lint_cap && denied_lint(&output.0) {
         *lint_cap = true;
-        cap_lints(&mut command);
-        output = to_check_output(command.output()?);
+        add_rustflags(&mut command, lint_flags, *lint_cap);
+        output = to_check_output(interrupt::output(&mut command)?);
+    }
+    if output.1 == Some(0) && needs_replay(&output.0) {
+        let mut replay = to_check_output(interrupt::output(&mut command)?);
+        if replay.1 == Some(0) {
+            keep_freshness(&mut replay.0, &output.0);
+            output = replay;
+        }
+    }
+
+    attribute_units(&mut output.0, &args.check_flags.platforms());
+
+    relocate_paths(args, sandbox, &mut output.0)?;
+    Ok(output)
+}
+
+/// The root of the workspace being compiled, and the directory the compiler is run from.
+fn compiler_dirs(args: &FixitArgs, sandbox: Option<&Sandbox>) -> CargoResult<(PathBuf, PathBuf)> {
+    Ok(match sandbox {
+        Some(sandbox) => (sandbox.root().to_owned(), sandbox.cwd().to_owned()),
+        None => (args.workspace_root.clone(), env::current_dir()?),
+    })
+}
+
+/// Makes the paths in diagnostics relative to the current directory, as the compiler reports
+/// them relative to the workspace root.
+///
+/// The current directory is outside the workspace whenever [`Fixer`](crate::ops::fixer::Fixer)
+/// is given a manifest path, and inside it when running from a subdirectory. Without this, fixes
+/// would be written to the same relative path under the current directory instead.
+fn relocate_paths(
+    args: &FixitArgs,
+    sandbox: Option<&Sandbox>,
+    messages: &mut [CheckOutput],
+) -> CargoResult<()> {
+    let (root, cwd) = compiler_dirs(args, sandbox)?;
+    if root.as_os_str().is_empty() || root == cwd {
+        return Ok(());
+    }
+    fn relocate(diagnostic: &mut Diagnostic, root: &Path, cwd: &Path) {
+        for span in &mut diagnostic.spans {
+            if Path::new(&span.file_name).is_relative() {
+                let path = root.join(&span.file_name);
+                span.file_name = path
+                    .strip_prefix(cwd)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .into_owned();
+            }
+        }
+        for child in &mut diagnostic.children {
+            relocate(child, root, cwd);
+        }
+    }
+    for message in messages {
+        if let CheckOutput::Message(message) = message {
+            relocate(&mut message.message.diagnostic, &root, &cwd);
+        }
     }
-
-    Ok(output)
+    Ok(())
 }
 
 fn print_built(args: &FixitArgs, messages: &[CheckOutput]) -> CargoResult<()> {
@@ -500,16 +2004,15 @@ fn print_built(args: &FixitArgs, messages: &[CheckOutput]) -> CargoResult<()> {
                     let kind = if 1 < kind.len() {
                         "lib" // HACK: if its multiple, it is only a lib
                     } else {
-                        match &kind[0] {
-                            TargetKind::Bin => "bin",
-                            TargetKind::Test => "test",
-                            TargetKind::Bench => "bench",
-                            TargetKind::Example => "example",
-                            TargetKind::CustomBuild => "custom-build",
-                            TargetKind::Lib(_) => "lib",
-                        }
+                        kind[0].as_str()
                     };
-                    shell::status("Checked", format!("{pkg_id} - {name} ({kind})"))?;
+                    let platform = a
+                        .build_unit
+                        .platform
+                        .as_ref()
+                        .map(|platform| format!(" for {platform}"))
+                        .unwrap_or_default();
+                    shell::status("Checked", format!("{pkg_id} - {name} ({kind}){platform}"))?;
                 }
             }
         }
@@ -518,19 +2021,28 @@ fn print_built(args: &FixitArgs, messages: &[CheckOutput]) -> CargoResult<()> {
     Ok(())
 }
 
-/// Applies the original lint cap while preserving existing compiler flags.
-fn cap_lints(command: &mut Command) {
+/// Applies lint flags and the original lint cap while preserving existing compiler flags.
+fn add_rustflags(command: &mut Command, lint_flags: &[String], lint_cap: bool) {
+    let mut added = lint_flags.to_vec();
+    if lint_cap {
+        added.push("--cap-lints=warn".to_owned());
+    }
+    if added.is_empty() {
+        return;
+    }
+
     if let Ok(flags) = env::var("CARGO_ENCODED_RUSTFLAGS") {
         let separator = if flags.is_empty() { "" } else { "\u{1f}" };
         command.env(
             "CARGO_ENCODED_RUSTFLAGS",
-            format!("{flags}{separator}--cap-lints=warn"),
+            format!("{flags}{separator}{}", added.join("\u{1f}")),
         );
     } else {
         command.env(
             "RUSTFLAGS",
             format!(
-                "--cap-lints=warn {}",
+                "{} {}",
+                added.join(" "),
                 env::var("RUSTFLAGS").unwrap_or("".to_owned())
             ),
         );
@@ -560,12 +2072,12 @@ fn to_check_output(output: std::process::Output) -> (Vec<CheckOutput>, Option<i3
 fn collect_diagnostics(
     messages: impl Iterator<Item = CheckOutput>,
     finished: &BTreeSet<UnitId>,
-    primary_packages: &PrimaryPackages,
+    filter: &FixFilter,
+    sandbox: Option<&Sandbox>,
     active_units: &mut IndexMap<UnitId, ActiveState>,
     max_iterations: usize,
+    skipped: &mut IndexSet<String>,
 ) -> (BuildUnitErrors, BuildUnitSuggestions) {
-    let only = HashSet::new();
-
     let mut suggestions = IndexMap::new();
     let mut errors = IndexMap::new();
 
@@ -595,120 +2107,548 @@ fn collect_diagnostics(
                     build_unit
                 );
                 let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
-                if let Some(rendered) = diagnostic.rendered {
-                    errors.insert(rendered);
-                }
+                errors.extend(UnfixedDiagnostic::new(diagnostic));
                 continue;
             }
         }
 
-        if !primary_packages.contains(&build_unit.package_id) {
+        if filter.is_migration_noise(&build_unit.package_id, &diagnostic) {
+            trace!("rejecting migration lint for build unit `{build_unit:?}` not being migrated");
+            continue;
+        }
+
+        if filter.is_deferred(&diagnostic) {
+            trace!(
+                "deferring diagnostic for a lint not in focus: {:?}",
+                diagnostic.code
+            );
+            continue;
+        }
+
+        if !filter.primary_packages.contains(&build_unit.package_id) {
             trace!(
                 "rejecting build unit `{:?}` not selected by the user",
                 build_unit
             );
             let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
-            if let Some(rendered) = diagnostic.rendered {
-                errors.insert(rendered);
-            }
+            errors.extend(UnfixedDiagnostic::new(diagnostic));
+            continue;
+        }
+
+        let rules = filter.rules(&diagnostic);
+        let code = diagnostic.code.as_ref().map(|code| code.code.as_str());
+        if !rules.lint_filter.is_selected(code) {
+            trace!("rejecting as the lint is not selected: {code:?}");
+            let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
+            errors.extend(UnfixedDiagnostic::new(diagnostic));
             continue;
         }
 
-        let filter = if env::var("__CARGO_FIX_YOLO").is_ok() {
-            rustfix::Filter::Everything
+        let mut diagnostic = diagnostic;
+        let allowed = if filter.speculative {
+            rules.applicability.max(Applicability::MaybeIncorrect)
         } else {
-            rustfix::Filter::MachineApplicableOnly
+            rules.applicability
         };
-        let Some(suggestion) = collect_suggestions(&diagnostic, &only, filter) else {
-            trace!("rejecting as not a MachineApplicable diagnosis: {diagnostic:?}");
+        let applicability = allowed.restrict(&mut diagnostic);
+        // The lints were already selected above, so rustfix is not asked to filter them again.
+        let (Some(applicability), Some(suggestion)) = (
+            applicability,
+            collect_suggestions(&diagnostic, &HashSet::new(), rustfix::Filter::Everything),
+        ) else {
+            trace!(
+                "rejecting as not a `{}` diagnosis: {diagnostic:?}",
+                rules.applicability
+            );
             let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
-            if let Some(rendered) = diagnostic.rendered {
-                errors.insert(rendered);
-            }
+            errors.extend(UnfixedDiagnostic::new(diagnostic));
             continue;
         };
 
-        let mut file_names = suggestion
-            .solutions
-            .iter()
-            .flat_map(|s| s.replacements.iter())
-            .map(|r| &r.snippet.file_name);
-
-        let Some(file_name) = file_names.next() else {
+        let file_names = touched_files(&suggestion);
+        let Some(file_name) = file_names.first().map(|f| (*f).to_owned()) else {
             trace!("rejecting as it has no solutions {:?}", suggestion);
             let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
-            if let Some(rendered) = diagnostic.rendered {
-                errors.insert(rendered);
-            }
+            errors.extend(UnfixedDiagnostic::new(diagnostic));
             continue;
         };
 
-        if !file_names.all(|f| f == file_name) {
-            trace!("rejecting as it changes multiple files: {:?}", suggestion);
+        if filter.is_unchanged(sandbox, &suggestion) {
+            trace!("rejecting as it changes lines outside of `--since` or `--staged`");
             let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
-            if let Some(rendered) = diagnostic.rendered {
-                errors.insert(rendered);
-            }
+            errors.extend(UnfixedDiagnostic::new(diagnostic));
             continue;
         }
 
-        let file_path = Path::new(&file_name);
-        // Do not write into registry cache. See rust-lang/cargo#9857.
-        if let Ok(home) = env::var("CARGO_HOME") {
-            if file_path.starts_with(home) {
-                let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
-                if let Some(rendered) = diagnostic.rendered {
-                    errors.insert(rendered);
-                }
-                continue;
-            }
+        if let Some(file_name) = file_names
+            .iter()
+            .find(|file_name| filter.is_dirty(&original_path(sandbox, file_name)))
+        {
+            trace!("rejecting as it changes `{file_name}` which has uncommitted changes");
+            skipped.insert((*file_name).to_owned());
+            let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
+            errors.extend(UnfixedDiagnostic::new(diagnostic));
+            continue;
         }
 
-        if file_path.is_absolute() {
-            if let Some(sysroot) = get_sysroot() {
-                if file_path.starts_with(sysroot) {
-                    let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
-                    if let Some(rendered) = diagnostic.rendered {
-                        errors.insert(rendered);
-                    }
-                    continue;
-                }
-            }
+        if let Some(file_name) = file_names
+            .iter()
+            .find(|file_name| filter.is_excluded(file_name))
+        {
+            trace!("rejecting as it changes `{file_name}` which is excluded by `fixit.toml`");
+            let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
+            errors.extend(UnfixedDiagnostic::new(diagnostic));
+            continue;
+        }
+
+        if let Some(file_name) = file_names
+            .iter()
+            .find(|file_name| !is_writable(sandbox, file_name))
+        {
+            trace!("rejecting as it changes `{file_name}` which can't be written to");
+            let errors = errors.entry(unit_id).or_insert_with(IndexSet::new);
+            errors.extend(UnfixedDiagnostic::new(diagnostic));
+            continue;
         }
 
         let unit_suggestions = suggestions
             .entry(unit_id.clone())
             .or_insert(IndexMap::new());
         unit_suggestions
-            .entry(file_name.to_owned())
+            .entry(file_name)
             .or_insert_with(IndexSet::new)
-            .insert((suggestion, diagnostic.rendered));
+            .insert(FixSuggestion {
+                suggestion,
+                applicability,
+                speculative: rules.applicability < applicability,
+                code: diagnostic.code.map(|code| code.code),
+                rendered: diagnostic.rendered,
+            });
     }
 
     (errors, suggestions)
 }
 
+/// Whether fixes may be written to a file reported by the compiler.
+fn is_writable(sandbox: Option<&Sandbox>, file_name: &str) -> bool {
+    let file_path = Path::new(file_name);
+    // Do not write into registry cache. See rust-lang/cargo#9857.
+    if let Ok(home) = env::var("CARGO_HOME") {
+        if file_path.starts_with(home) {
+            return false;
+        }
+    }
+
+    // Only the copied workspace may be written to with `--dry-run` or `--sandbox`.
+    if sandbox.is_some_and(|sandbox| !sandbox.contains(file_name)) {
+        return false;
+    }
+
+    if file_path.is_absolute() {
+        if let Some(sysroot) = get_sysroot() {
+            if file_path.starts_with(sysroot) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// A suggestion applied in the latest round of fixes.
+#[derive(Debug)]
+struct AppliedFix {
+    unit_id: UnitId,
+    key: SuggestionKey,
+    code: Option<String>,
+    applicability: Applicability,
+    speculative: bool,
+    /// The edits made to each file
+    parts: IndexMap<String, Suggestion>,
+}
+
+/// The fixes applied since the last successful check, so they can be undone individually.
+#[derive(Debug, Default)]
+struct Round {
+    fixes: Vec<AppliedFix>,
+    /// Contents of each changed file before the round
+    sources: IndexMap<String, String>,
+    /// The unit whose speculative suggestions are part of the round
+    speculation: Option<Speculation>,
+}
+
+impl Round {
+    /// Rewrites the changed files with only the `selected` fixes applied.
+    fn write(&self, sandbox: Option<&Sandbox>, selected: &[usize]) -> CargoResult<()> {
+        let mut fixes = self
+            .sources
+            .iter()
+            .map(|(file, source)| (file.as_str(), CodeFix::new(source)))
+            .collect::<IndexMap<_, _>>();
+        for &i in selected {
+            for (file, part) in &self.fixes[i].parts {
+                fixes
+                    .get_mut(file.as_str())
+                    .expect("sources are recorded for every changed file")
+                    .apply(part)?;
+            }
+        }
+        for (file, fixed) in fixes {
+            paths::write(resolve_path(sandbox, file), fixed.finish()?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Speculative suggestions applied to a unit, to be kept only if it still compiles cleanly.
+#[derive(Debug)]
+struct Speculation {
+    unit_id: UnitId,
+    /// The warnings of the unit before the round
+    warnings: Vec<(Option<String>, String)>,
+}
+
+impl Speculation {
+    /// Whether the unit has a warning in `messages` that it did not have before.
+    fn adds_warnings(&self, messages: &[CheckOutput]) -> bool {
+        let mut before = self.warnings.clone();
+        let after = unit_warnings(messages).remove(&self.unit_id);
+        after.into_iter().flatten().any(|warning| {
+            match before.iter().position(|known| *known == warning) {
+                Some(i) => {
+                    before.swap_remove(i);
+                    false
+                }
+                None => true,
+            }
+        })
+    }
+}
+
+/// The lint and message of every diagnostic, by unit.
+fn unit_warnings(messages: &[CheckOutput]) -> HashMap<UnitId, Vec<(Option<String>, String)>> {
+    let mut warnings: HashMap<_, Vec<_>> = HashMap::new();
+    for message in messages {
+        let CheckOutput::Message(Message {
+            build_unit,
+            message: MessageDiagnostic { diagnostic, .. },
+        }) = message
+        else {
+            continue;
+        };
+        warnings
+            .entry(UnitId::from_message(build_unit))
+            .or_default()
+            .push((
+                diagnostic.code.as_ref().map(|code| code.code.clone()),
+                diagnostic.message.clone(),
+            ));
+    }
+    warnings
+}
+
+/// Holds back the speculative suggestions of units that have others to apply first, so the
+/// others are not blamed for what the speculative ones break.
+fn defer_speculative(suggestions: &mut BuildUnitSuggestions) {
+    for unit_suggestions in suggestions.values_mut() {
+        let has_others = unit_suggestions
+            .values()
+            .flatten()
+            .any(|fix| !fix.speculative);
+        if !has_others {
+            continue;
+        }
+        for file_suggestions in unit_suggestions.values_mut() {
+            file_suggestions.retain(|fix| !fix.speculative);
+        }
+        unit_suggestions.retain(|_file, file_suggestions| !file_suggestions.is_empty());
+    }
+}
+
+/// Finds the fixes from the latest round that broke compilation, or added warnings to the unit
+/// speculated on, and undoes only those.
+///
+/// Returns `false` if the code doesn't compile even without any of the round's fixes.
+fn bisect_round(
+    args: &FixitArgs,
+    sandbox: Option<&Sandbox>,
+    lint_flags: &[String],
+    lint_cap: &mut bool,
+    round: &mut Round,
+    active_units: &mut IndexMap<UnitId, ActiveState>,
+    broken: &mut HashSet<SuggestionKey>,
+) -> CargoResult<bool> {
+    let candidates = (0..round.fixes.len()).collect::<Vec<_>>();
+    let mut kept = Vec::new();
+    let mut breaking = Vec::new();
+    {
+        let mut compiles = |selected: &[usize]| -> CargoResult<bool> {
+            trace!("checking with fixes {selected:?}");
+            round.write(sandbox, selected)?;
+            let (messages, exit_code) = check(args, sandbox, lint_flags, lint_cap)?;
+            let adds_warnings = round
+                .speculation
+                .as_ref()
+                .is_some_and(|speculation| speculation.adds_warnings(&messages));
+            Ok(exit_code == Some(0) && !adds_warnings)
+        };
+        if !compiles(&[])? {
+            return Ok(false);
+        }
+        // All of the candidates together are already known to fail.
+        if candidates.len() == 1 {
+            breaking = candidates;
+        } else {
+            let (left, right) = candidates.split_at(candidates.len() / 2);
+            find_breaking(&mut compiles, &mut kept, left, &mut breaking)?;
+            find_breaking(&mut compiles, &mut kept, right, &mut breaking)?;
+        }
+    }
+    kept.sort_unstable();
+    round.write(sandbox, &kept)?;
+
+    for i in breaking {
+        let fix = &round.fixes[i];
+        let Some(state) = active_units.get_mut(&fix.unit_id) else {
+            continue;
+        };
+        for file in fix.parts.keys() {
+            if let Some(file_state) = state.snapshots.get_mut(file) {
+                file_state.fixes -= 1;
+                if fix.applicability != Applicability::Machine {
+                    file_state.risky_fixes -= 1;
+                }
+            }
+        }
+        state
+            .snapshots
+            .retain(|_file, file_state| 0 < file_state.fixes);
+
+        let (file, part) = fix.parts.first().expect("fixes edit at least one file");
+        let start = part
+            .solutions
+            .iter()
+            .flat_map(|solution| &solution.replacements)
+            .map(|replacement| replacement.snippet.line_range.start)
+            .next()
+            .unwrap_or(rustfix::LinePosition { line: 0, column: 0 });
+        let lint = match &fix.code {
+            Some(code) => format!("`{code}` "),
+            None => String::new(),
+        };
+        // Speculative fixes are expected to fail at times, and are reported as remaining.
+        if !fix.speculative {
+            shell::warn(format!(
+                "dropped {lint}fix at {file}:{}:{} as it broke compilation: {}",
+                start.line, start.column, part.message
+            ))?;
+        }
+        args.emit(&Event::SuggestionDropped {
+            package_id: fix.unit_id.package_id(),
+            target_kind: fix.unit_id.target_kind().as_str(),
+            file,
+            line: start.line,
+            column: start.column,
+            code: fix.code.as_deref(),
+            message: &part.message,
+        })?;
+        broken.insert(fix.key.clone());
+    }
+    round.fixes.clear();
+
+    Ok(true)
+}
+
+/// Splits `candidates` until it finds the fixes that fail to compile alongside those `kept`.
+fn find_breaking(
+    compiles: &mut impl FnMut(&[usize]) -> CargoResult<bool>,
+    kept: &mut Vec<usize>,
+    candidates: &[usize],
+    breaking: &mut Vec<usize>,
+) -> CargoResult<()> {
+    let selected = kept.iter().chain(candidates).copied().collect::<Vec<_>>();
+    if compiles(&selected)? {
+        kept.extend(candidates);
+    } else if let [candidate] = candidates {
+        breaking.push(*candidate);
+    } else {
+        let (left, right) = candidates.split_at(candidates.len() / 2);
+        find_breaking(compiles, kept, left, breaking)?;
+        find_breaking(compiles, kept, right, breaking)?;
+    }
+    Ok(())
+}
+
+/// Reports suggestions the user declined, or that broke compilation, as remaining diagnostics.
+fn discard_rejected(
+    reviewer: Option<&Reviewer>,
+    broken: &HashSet<SuggestionKey>,
+    sandbox: Option<&Sandbox>,
+    suggestions: &mut BuildUnitSuggestions,
+    errors: &mut BuildUnitErrors,
+) {
+    let mut sources = IndexMap::new();
+    for (unit_id, unit_suggestions) in suggestions.iter_mut() {
+        for file_suggestions in unit_suggestions.values_mut() {
+            file_suggestions.retain(|fix| {
+                if !read_sources(&mut sources, sandbox, &fix.suggestion) {
+                    return true;
+                }
+                let key = SuggestionKey::new(&sources, fix.code.as_deref(), &fix.suggestion);
+                if broken.contains(&key) || reviewer.is_some_and(|r| r.is_skipped(&key)) {
+                    trace!("rejecting as it was skipped: {:?}", fix.suggestion);
+                    let errors = errors.entry(unit_id.clone()).or_default();
+                    errors.extend(fix.to_unfixed());
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        unit_suggestions.retain(|_file, file_suggestions| !file_suggestions.is_empty());
+    }
+    suggestions.retain(|_unit_id, unit_suggestions| !unit_suggestions.is_empty());
+}
+
+/// Every file a suggestion edits, in the order they are first edited.
+pub(crate) fn touched_files(suggestion: &Suggestion) -> IndexSet<&str> {
+    suggestion
+        .solutions
+        .iter()
+        .flat_map(|solution| &solution.replacements)
+        .map(|replacement| replacement.snippet.file_name.as_str())
+        .collect()
+}
+
+/// Splits a suggestion into the edits it makes to each file.
+pub(crate) fn split_by_file(suggestion: &Suggestion) -> IndexMap<String, Suggestion> {
+    touched_files(suggestion)
+        .into_iter()
+        .map(|file| {
+            let part = Suggestion {
+                message: suggestion.message.clone(),
+                snippets: suggestion
+                    .snippets
+                    .iter()
+                    .filter(|snippet| snippet.file_name == file)
+                    .cloned()
+                    .collect(),
+                solutions: suggestion
+                    .solutions
+                    .iter()
+                    .map(|solution| rustfix::Solution {
+                        message: solution.message.clone(),
+                        replacements: solution
+                            .replacements
+                            .iter()
+                            .filter(|replacement| replacement.snippet.file_name == file)
+                            .cloned()
+                            .collect(),
+                    })
+                    .collect(),
+            };
+            (file.to_owned(), part)
+        })
+        .collect()
+}
+
+/// Loads the current contents of every file a suggestion edits.
+///
+/// Returns `false` if any of them can't be read.
+fn read_sources(
+    sources: &mut IndexMap<String, String>,
+    sandbox: Option<&Sandbox>,
+    suggestion: &Suggestion,
+) -> bool {
+    for file in touched_files(suggestion) {
+        if sources.contains_key(file) {
+            continue;
+        }
+        match paths::read(&resolve_path(sandbox, file)) {
+            Ok(source) => {
+                sources.insert(file.to_owned(), source);
+            }
+            Err(e) => {
+                warn!("failed to read `{}`: {}", file, e);
+                return false;
+            }
+        }
+    }
+    true
+}
+
 #[tracing::instrument(skip_all)]
 fn fix_suggestions(
-    unit_suggestions: &IndexMap<String, IndexSet<(Suggestion, Option<String>)>>,
+    args: &FixitArgs,
+    unit_id: &UnitId,
+    unit_suggestions: &IndexMap<String, IndexSet<FixSuggestion>>,
+    sandbox: Option<&Sandbox>,
     state: &mut ActiveState,
+    mut reviewer: Option<&mut Reviewer>,
+    round: &mut Round,
 ) -> CargoResult<bool> {
-    let mut made_changes = false;
-    for (file, suggestions) in unit_suggestions {
-        let source = match paths::read(file.as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                warn!("failed to read `{}`: {}", file, e);
+    let mut sources = IndexMap::new();
+    let mut fixes: IndexMap<String, CodeFix> = IndexMap::new();
+    let mut applied: IndexMap<String, Vec<(&FixSuggestion, Suggestion)>> = IndexMap::new();
+    for suggestions in unit_suggestions.values() {
+        let mut reviewed = Vec::new();
+        for fix in suggestions {
+            if !read_sources(&mut sources, sandbox, &fix.suggestion) {
                 continue;
             }
-        };
-
-        let mut fixed = CodeFix::new(&source);
-        let mut num_fixes = 0;
+            let suggestion = match reviewer.as_deref_mut() {
+                Some(reviewer) => {
+                    let candidate = Candidate {
+                        sources: &sources,
+                        suggestion: &fix.suggestion,
+                        applicability: fix.applicability,
+                        code: fix.code.as_deref(),
+                        rendered: fix.rendered.as_deref(),
+                    };
+                    match reviewer.review(&candidate)? {
+                        Some(suggestion) => Cow::Owned(suggestion),
+                        None => continue,
+                    }
+                }
+                None => Cow::Borrowed(&fix.suggestion),
+            };
+            reviewed.push((fix, suggestion));
+        }
 
-        for (suggestion, _rendered) in suggestions.iter().rev() {
-            match fixed.apply(suggestion) {
-                Ok(()) => num_fixes += 1,
+        for (fix, suggestion) in reviewed.iter().rev() {
+            let parts = split_by_file(suggestion);
+            // Stage every file's edits so a suggestion is applied to all of its files or none.
+            let mut staged = Vec::new();
+            let mut result = Ok(());
+            for (part_file, part) in &parts {
+                let mut fixed = fixes
+                    .get(part_file)
+                    .cloned()
+                    .unwrap_or_else(|| CodeFix::new(&sources[part_file]));
+                if let Err(e) = fixed.apply(part) {
+                    result = Err(e);
+                    break;
+                }
+                staged.push((part_file.clone(), fixed));
+            }
+            match result {
+                Ok(()) => {
+                    fixes.extend(staged);
+                    for (part_file, part) in &parts {
+                        applied
+                            .entry(part_file.clone())
+                            .or_default()
+                            .push((fix, part.clone()));
+                    }
+                    round.fixes.push(AppliedFix {
+                        unit_id: unit_id.clone(),
+                        key: SuggestionKey::new(&sources, fix.code.as_deref(), &fix.suggestion),
+                        code: fix.code.clone(),
+                        applicability: fix.applicability,
+                        speculative: fix.speculative,
+                        parts,
+                    });
+                }
                 Err(rustfix::Error::AlreadyReplaced {
                     is_identical: true, ..
                 }) => {}
@@ -717,15 +2657,50 @@ fn fix_suggestions(
                 }
             }
         }
-        if fixed.modified() {
-            let new_source = fixed.finish()?;
-            let file_state = state.snapshots.entry(file.clone()).or_insert(File {
-                fixes: 0,
-                original_source: source,
-            });
-            paths::write(file, new_source)?;
-            made_changes = true;
-            file_state.fixes += num_fixes;
+    }
+
+    let mut made_changes = false;
+    for (file, fixed) in fixes {
+        let new_source = fixed.finish()?;
+        let source = sources
+            .get(&file)
+            .cloned()
+            .expect("sources are read before fixing");
+        round.sources.insert(file.clone(), source.clone());
+        let file_state = state.snapshots.entry(file.clone()).or_insert(File {
+            fixes: 0,
+            risky_fixes: 0,
+            original_source: source,
+        });
+        paths::write(resolve_path(sandbox, &file), new_source)?;
+        made_changes = true;
+
+        let applied = applied.swap_remove(&file).unwrap_or_default();
+        file_state.fixes += applied.len() as u32;
+        file_state.risky_fixes += applied
+            .iter()
+            .filter(|(fix, _)| fix.applicability != Applicability::Machine)
+            .count() as u32;
+
+        for (fix, suggestion) in applied {
+            args.emit(&Event::SuggestionApplied {
+                package_id: unit_id.package_id(),
+                target_kind: unit_id.target_kind().as_str(),
+                file: &file,
+                code: fix.code.as_deref(),
+                applicability: fix.applicability.as_str(),
+                message: &suggestion.message,
+                replacements: suggestion
+                    .solutions
+                    .iter()
+                    .flat_map(|solution| &solution.replacements)
+                    .map(|replacement| AppliedReplacement {
+                        byte_start: replacement.snippet.range.start,
+                        byte_end: replacement.snippet.range.end,
+                        replacement: &replacement.replacement,
+                    })
+                    .collect(),
+            })?;
         }
     }
 
@@ -739,15 +2714,31 @@ struct UnitId {
 
 #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 struct UnitIdInner {
-    // HACK: this should also track
-    // - test-mode or not
-    // - platform
-    // - host vs target mode
     package_id: String,
     target_kind: TargetKind,
+    /// Built with `--test`, so a library's unit tests are fixed separately from the library
+    test: bool,
+    /// The target triple, or `None` for the host
+    platform: Option<String>,
 }
 
 impl UnitId {
+    fn new(
+        package_id: String,
+        target_kind: TargetKind,
+        test: bool,
+        platform: Option<String>,
+    ) -> Self {
+        Self {
+            inner: std::sync::Arc::new(UnitIdInner {
+                package_id,
+                target_kind,
+                test,
+                platform,
+            }),
+        }
+    }
+
     fn from_message(build_unit: &BuildUnit) -> Self {
         // HACK: just collapse all libs to one kind since we can't distinguish them
         let target_kind = build_unit
@@ -760,19 +2751,67 @@ impl UnitId {
             target_kind => target_kind.clone(),
         };
 
+        Self::new(
+            build_unit.package_id.clone(),
+            target_kind,
+            build_unit.profile.test,
+            build_unit.platform.clone(),
+        )
+    }
+
+    fn package_id(&self) -> &str {
+        &self.inner.package_id
+    }
+
+    fn target_kind(&self) -> &TargetKind {
+        &self.inner.target_kind
+    }
+
+    fn is_test(&self) -> bool {
+        self.inner.test
+    }
+
+    fn platform(&self) -> Option<&str> {
+        self.inner.platform.as_deref()
+    }
+}
+
+/// The units Cargo builds for each target.
+#[derive(Debug)]
+struct UnitVariants {
+    /// Whether libraries and binaries are also built as unit tests
+    unit_tests: bool,
+    /// The requested target triples, empty when building for the host
+    platforms: Vec<String>,
+    /// Packages whose libraries are also built for the host when cross-compiling
+    host_packages: HashSet<String>,
+}
+
+impl UnitVariants {
+    fn new(metadata: &Metadata, flags: &CheckFlags) -> Self {
+        let platforms = flags.platforms();
+        let host_packages = if platforms.is_empty() {
+            HashSet::new()
+        } else {
+            host_packages(metadata)
+        };
         Self {
-            inner: std::sync::Arc::new(UnitIdInner {
-                package_id: build_unit.package_id.clone(),
-                target_kind,
-            }),
+            unit_tests: flags.checks_unit_tests(),
+            platforms,
+            host_packages,
         }
     }
 
-    fn from_metadata(
+    /// The units that may be built for a kind of a package's target.
+    ///
+    /// Not all of them are built in every run, but those that are not simply have nothing to fix.
+    fn units(
+        &self,
         package: &cargo_metadata::Package,
-        target_kind: &cargo_metadata::TargetKind,
-    ) -> Self {
-        let target_kind = match target_kind {
+        target: &cargo_metadata::Target,
+        kind: &cargo_metadata::TargetKind,
+    ) -> Vec<UnitId> {
+        let target_kind = match kind {
             cargo_metadata::TargetKind::Bin => TargetKind::Bin,
             cargo_metadata::TargetKind::Test => TargetKind::Test,
             cargo_metadata::TargetKind::Bench => TargetKind::Bench,
@@ -787,22 +2826,83 @@ impl UnitId {
             | cargo_metadata::TargetKind::ProcMacro => TargetKind::Lib(CrateType::Lib),
             target_kind => TargetKind::Lib(CrateType::Other(target_kind.to_string())),
         };
+        let modes: &[bool] = match target_kind {
+            TargetKind::Test | TargetKind::Bench => &[true],
+            TargetKind::Bin | TargetKind::Lib(_) if self.unit_tests && target.test => {
+                &[false, true]
+            }
+            _ => &[false],
+        };
+        let for_host = matches!(target_kind, TargetKind::CustomBuild)
+            || matches!(kind, cargo_metadata::TargetKind::ProcMacro);
+        let mut platforms = Vec::new();
+        if for_host || self.platforms.is_empty() {
+            platforms.push(None);
+        } else {
+            platforms.extend(self.platforms.iter().cloned().map(Some));
+            if matches!(target_kind, TargetKind::Lib(_))
+                && self.host_packages.contains(&package.id.repr)
+            {
+                platforms.push(None);
+            }
+        }
 
-        Self {
-            inner: std::sync::Arc::new(UnitIdInner {
-                package_id: package.id.repr.clone(),
-                target_kind,
-            }),
+        let mut units = Vec::new();
+        for platform in platforms {
+            for test in modes {
+                units.push(UnitId::new(
+                    package.id.repr.clone(),
+                    target_kind.clone(),
+                    *test,
+                    platform.clone(),
+                ));
+            }
         }
+        units
     }
+}
 
-    fn package_id(&self) -> &str {
-        &self.inner.package_id
+/// Packages whose libraries are needed by build scripts and proc-macros.
+fn host_packages(metadata: &Metadata) -> HashSet<String> {
+    let by_path = metadata
+        .packages
+        .iter()
+        .map(|package| (manifest_path_to_dep_path(&package.manifest_path), package))
+        .collect::<HashMap<_, _>>();
+    let path_dependency = |dependency: &cargo_metadata::Dependency| {
+        dependency
+            .path
+            .as_deref()
+            .and_then(|path| by_path.get(path).copied())
+    };
+    let mut stack = Vec::new();
+    for package in &metadata.packages {
+        let is_proc_macro = package.targets.iter().any(|target| target.is_proc_macro());
+        stack.extend(
+            package
+                .dependencies
+                .iter()
+                .filter(|dependency| match dependency.kind {
+                    cargo_metadata::DependencyKind::Build => true,
+                    cargo_metadata::DependencyKind::Normal => is_proc_macro,
+                    _ => false,
+                })
+                .filter_map(path_dependency),
+        );
     }
-
-    fn target_kind(&self) -> &TargetKind {
-        &self.inner.target_kind
+    let mut host_packages = HashSet::new();
+    while let Some(package) = stack.pop() {
+        if host_packages.insert(package.id.repr.clone()) {
+            stack.extend(
+                package
+                    .dependencies
+                    .iter()
+                    .filter(|dependency| dependency.kind == cargo_metadata::DependencyKind::Normal)
+                    .filter_map(path_dependency),
+            );
+        }
     }
+    host_packages
 }
 
 #[derive(Debug)]
@@ -812,13 +2912,14 @@ struct UnitGraph {
 }
 
 impl UnitGraph {
-    fn flat(metadata: &Metadata) -> Self {
+    fn flat(metadata: &Metadata, variants: &UnitVariants) -> Self {
         let mut dependencies = BTreeMap::default();
         for package in &metadata.packages {
             for target in &package.targets {
                 for kind in &target.kind {
-                    let unit_id = UnitId::from_metadata(package, kind);
-                    dependencies.insert(unit_id, Default::default());
+                    for unit_id in variants.units(package, target, kind) {
+                        dependencies.insert(unit_id, Default::default());
+                    }
                 }
             }
         }
@@ -829,91 +2930,99 @@ impl UnitGraph {
         }
     }
 
-    fn new(metadata: &Metadata) -> Self {
+    fn new(metadata: &Metadata, variants: &UnitVariants) -> Self {
         let mut dependencies = BTreeMap::default();
-        let mut path_to_lib_unit_ids = BTreeMap::default();
+        let mut package_units = Vec::new();
+        let mut build_script_unit_ids = HashMap::new();
+        let mut lib_unit_ids = HashMap::new();
+        let mut path_to_package_id = BTreeMap::default();
         for package in &metadata.packages {
-            let mut build_script_unit_id = None;
-            let mut lib_unit_ids = BTreeSet::new();
-            let mut other_unit_ids = BTreeSet::new();
+            let mut unit_ids = BTreeSet::new();
             for target in &package.targets {
                 for kind in &target.kind {
-                    let unit_id = UnitId::from_metadata(package, kind);
-                    if matches!(unit_id.target_kind(), TargetKind::CustomBuild) {
-                        build_script_unit_id = Some(unit_id);
-                    } else if matches!(unit_id.target_kind(), TargetKind::Lib(_)) {
-                        lib_unit_ids.insert(unit_id);
-                    } else {
-                        other_unit_ids.insert(unit_id);
-                    }
+                    unit_ids.extend(variants.units(package, target, kind));
                 }
             }
-
-            for unit_id in other_unit_ids {
-                let deps = if !lib_unit_ids.is_empty() {
-                    lib_unit_ids.clone()
-                } else {
-                    build_script_unit_id.clone().into_iter().collect()
-                };
-                dependencies.insert(unit_id, deps);
-            }
-            if !lib_unit_ids.is_empty() {
-                let path_source = manifest_path_to_dep_path(&package.manifest_path);
-                path_to_lib_unit_ids.insert(path_source.to_owned(), lib_unit_ids.clone());
-                for unit_id in lib_unit_ids {
-                    let deps = build_script_unit_id.clone().into_iter().collect();
-                    dependencies.insert(unit_id, deps);
+            for unit_id in &unit_ids {
+                if matches!(unit_id.target_kind(), TargetKind::CustomBuild) {
+                    build_script_unit_ids.insert(package.id.repr.as_str(), unit_id.clone());
+                } else if matches!(unit_id.target_kind(), TargetKind::Lib(_)) && !unit_id.is_test()
+                {
+                    let key = (
+                        package.id.repr.as_str(),
+                        unit_id.platform().map(str::to_owned),
+                    );
+                    lib_unit_ids.insert(key, unit_id.clone());
                 }
             }
-            if let Some(unit_id) = build_script_unit_id {
-                dependencies.insert(unit_id, Default::default());
+            let path_source = manifest_path_to_dep_path(&package.manifest_path);
+            path_to_package_id.insert(path_source.to_owned(), package.id.repr.as_str());
+            package_units.push((package, unit_ids));
+        }
+        // Proc-macro libraries are only built for the host.
+        let lib_unit_id = |package_id: &str, platform: Option<&str>| {
+            lib_unit_ids
+                .get(&(package_id, platform.map(str::to_owned)))
+                .or_else(|| lib_unit_ids.get(&(package_id, None)))
+                .cloned()
+        };
+
+        for (package, unit_ids) in &package_units {
+            let package_id = package.id.repr.as_str();
+            let build_script_unit_id = build_script_unit_ids.get(package_id).cloned();
+            for unit_id in unit_ids {
+                let deps = match unit_id.target_kind() {
+                    TargetKind::CustomBuild => BTreeSet::new(),
+                    TargetKind::Lib(_) if !unit_id.is_test() => {
+                        build_script_unit_id.clone().into_iter().collect()
+                    }
+                    // Unit tests are fixed after the library, as they share its files.
+                    _ => lib_unit_id(package_id, unit_id.platform())
+                        .or_else(|| build_script_unit_id.clone())
+                        .into_iter()
+                        .collect(),
+                };
+                dependencies.insert(unit_id.clone(), deps);
             }
         }
 
-        for package in &metadata.packages {
+        for (package, unit_ids) in &package_units {
             for dependency in &package.dependencies {
                 let Some(dep_path) = &dependency.path else {
                     continue;
                 };
-                let Some(dep_unit_ids) = path_to_lib_unit_ids.get(dep_path) else {
+                let Some(dep_package_id) = path_to_package_id.get(dep_path) else {
                     continue;
                 };
-                for target in &package.targets {
-                    for kind in &target.kind {
-                        let unit_id = UnitId::from_metadata(package, kind);
-                        let applies = match (&unit_id.target_kind(), &dependency.kind) {
-                            (TargetKind::CustomBuild, cargo_metadata::DependencyKind::Build) => {
-                                true
-                            }
-                            (TargetKind::Lib(_), cargo_metadata::DependencyKind::Normal) => true,
-                            (TargetKind::Lib(_), cargo_metadata::DependencyKind::Development) => {
-                                // HACK: should include for unit test variant except that would
-                                // cause cycles
-                                false
-                            }
-                            (TargetKind::Bin, cargo_metadata::DependencyKind::Normal) => true,
-                            (TargetKind::Bin, cargo_metadata::DependencyKind::Development) => {
-                                // HACK: for the unit test variant
-                                true
-                            }
-                            (TargetKind::Test, cargo_metadata::DependencyKind::Normal) => true,
-                            (TargetKind::Test, cargo_metadata::DependencyKind::Development) => true,
-                            (TargetKind::Bench, cargo_metadata::DependencyKind::Normal) => true,
-                            (TargetKind::Bench, cargo_metadata::DependencyKind::Development) => {
-                                true
-                            }
-                            (TargetKind::Example, cargo_metadata::DependencyKind::Normal) => true,
-                            (TargetKind::Example, cargo_metadata::DependencyKind::Development) => {
-                                true
-                            }
-                            _ => false,
-                        };
-                        if applies {
-                            dependencies
-                                .entry(unit_id)
-                                .or_default()
-                                .extend(dep_unit_ids.clone());
-                        }
+                for unit_id in unit_ids {
+                    let applies = match (unit_id.target_kind(), &dependency.kind) {
+                        (TargetKind::CustomBuild, cargo_metadata::DependencyKind::Build) => true,
+                        (TargetKind::CustomBuild, _) => false,
+                        (_, cargo_metadata::DependencyKind::Normal) => true,
+                        (
+                            TargetKind::Lib(_) | TargetKind::Bin,
+                            cargo_metadata::DependencyKind::Development,
+                        ) => unit_id.is_test(),
+                        (
+                            TargetKind::Test | TargetKind::Bench | TargetKind::Example,
+                            cargo_metadata::DependencyKind::Development,
+                        ) => true,
+                        _ => false,
+                    };
+                    if !applies {
+                        continue;
+                    }
+                    // Build scripts run on the host.
+                    let platform = if matches!(unit_id.target_kind(), TargetKind::CustomBuild) {
+                        None
+                    } else {
+                        unit_id.platform()
+                    };
+                    if let Some(dep_unit_id) = lib_unit_id(dep_package_id, platform) {
+                        dependencies
+                            .entry(unit_id.clone())
+                            .or_default()
+                            .insert(dep_unit_id);
                     }
                 }
             }
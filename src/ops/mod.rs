@@ -1,2 +1,7 @@
 pub mod check;
+pub mod fixer;
 pub mod fixit;
+pub(crate) mod review;
+pub(crate) mod verify;
+
+pub use fixer::{Driver, FileFixes, FixReport, Fixer, RemainingDiagnostic, Target};
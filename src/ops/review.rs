@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+use rustfix::{CodeFix, Solution, Suggestion};
+
+use crate::core::shell;
+use crate::ops::fixit::{split_by_file, SuggestionKey};
+use crate::util::applicability::Applicability;
+use crate::CargoResult;
+
+/// Asks the user whether each suggestion should be applied.
+///
+/// Decisions are remembered so that a diagnostic reported again on a later
+/// iteration is not asked about twice.
+#[derive(Debug, Default)]
+pub(crate) struct Reviewer {
+    decisions: HashMap<SuggestionKey, Decision>,
+    accepted_lints: HashSet<String>,
+    quit: bool,
+}
+
+#[derive(Debug)]
+enum Decision {
+    Skip,
+    Accept {
+        solution: usize,
+        placeholders: Vec<String>,
+    },
+}
+
+/// A suggestion waiting on the user's decision.
+#[derive(Debug)]
+pub(crate) struct Candidate<'a> {
+    /// Current contents of every file the suggestion edits
+    pub(crate) sources: &'a IndexMap<String, String>,
+    pub(crate) suggestion: &'a Suggestion,
+    pub(crate) applicability: Applicability,
+    pub(crate) code: Option<&'a str>,
+    pub(crate) rendered: Option<&'a str>,
+}
+
+impl Reviewer {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the user declined this suggestion, or stopped reviewing before seeing it.
+    pub(crate) fn is_skipped(&self, key: &SuggestionKey) -> bool {
+        match self.decisions.get(key) {
+            Some(Decision::Skip) => true,
+            Some(Decision::Accept { .. }) => false,
+            None => self.quit,
+        }
+    }
+
+    /// Returns the suggestion to apply, reduced to the chosen solution, or `None` to skip it.
+    pub(crate) fn review(&mut self, candidate: &Candidate<'_>) -> CargoResult<Option<Suggestion>> {
+        let key = SuggestionKey::new(candidate.sources, candidate.code, candidate.suggestion);
+        if let Some(decision) = self.decisions.get(&key) {
+            return Ok(decision.select(candidate.suggestion));
+        }
+        if self.quit {
+            return Ok(None);
+        }
+
+        let decision = self.ask(candidate)?;
+        let suggestion = decision.select(candidate.suggestion);
+        self.decisions.insert(key, decision);
+        Ok(suggestion)
+    }
+
+    fn ask(&mut self, candidate: &Candidate<'_>) -> CargoResult<Decision> {
+        let lint_accepted = candidate
+            .code
+            .is_some_and(|code| self.accepted_lints.contains(code));
+        let solutions = &candidate.suggestion.solutions;
+        if lint_accepted && solutions.len() == 1 && !has_placeholders(candidate, &solutions[0]) {
+            return Ok(Decision::Accept {
+                solution: 0,
+                placeholders: Vec::new(),
+            });
+        }
+
+        match candidate.rendered {
+            Some(rendered) => {
+                shell::print_ansi_stderr(format!("{}\n\n", rendered.trim_end()).as_bytes())?;
+            }
+            None => shell::note(&candidate.suggestion.message)?,
+        }
+        for (i, solution) in solutions.iter().enumerate() {
+            if 1 < solutions.len() {
+                shell::note(format!("solution {}: {}", i + 1, solution.message))?;
+            }
+            let suggestion = Suggestion {
+                message: candidate.suggestion.message.clone(),
+                snippets: candidate.suggestion.snippets.clone(),
+                solutions: vec![solution.clone()],
+            };
+            for (file, part) in split_by_file(&suggestion) {
+                let source = &candidate.sources[&file];
+                let mut fixed = CodeFix::new(source);
+                fixed.apply(&part)?;
+                shell::diff_stderr(&file, source, &fixed.finish()?)?;
+            }
+        }
+
+        let lint_choice = match candidate.code {
+            Some(code) if !lint_accepted && solutions.len() == 1 => {
+                format!(", [a]ll `{code}`")
+            }
+            _ => String::new(),
+        };
+        let question = if solutions.len() == 1 {
+            format!("Apply this fix? [y]es, [n]o{lint_choice}, [q]uit")
+        } else {
+            format!(
+                "Apply which fix? [1-{}], [n]o{lint_choice}, [q]uit",
+                solutions.len()
+            )
+        };
+
+        let solution = loop {
+            let Some(answer) = shell::prompt(&question)? else {
+                self.quit = true;
+                return Ok(Decision::Skip);
+            };
+            let answer = answer.trim();
+            match answer {
+                "y" | "yes" if solutions.len() == 1 => break 0,
+                "n" | "no" => return Ok(Decision::Skip),
+                "q" | "quit" => {
+                    self.quit = true;
+                    return Ok(Decision::Skip);
+                }
+                "a" | "all" if !lint_choice.is_empty() => {
+                    self.accepted_lints
+                        .extend(candidate.code.map(ToOwned::to_owned));
+                    break 0;
+                }
+                answer => match answer.parse::<usize>() {
+                    Ok(i) if 1 < solutions.len() && (1..=solutions.len()).contains(&i) => {
+                        break i - 1;
+                    }
+                    _ => shell::note(format!("unrecognized answer `{answer}`"))?,
+                },
+            }
+        };
+
+        let mut placeholders = Vec::new();
+        if has_placeholders(candidate, &solutions[solution]) {
+            for placeholder in solutions[solution]
+                .replacements
+                .iter()
+                .flat_map(|replacement| find_placeholders(&replacement.replacement))
+            {
+                let Some(answer) = shell::prompt(format!("Replace `{placeholder}` with:"))? else {
+                    self.quit = true;
+                    return Ok(Decision::Skip);
+                };
+                placeholders.push(answer);
+            }
+        }
+
+        Ok(Decision::Accept {
+            solution,
+            placeholders,
+        })
+    }
+}
+
+impl Decision {
+    fn select(&self, suggestion: &Suggestion) -> Option<Suggestion> {
+        let Self::Accept {
+            solution,
+            placeholders,
+        } = self
+        else {
+            return None;
+        };
+
+        let mut solution: Solution = suggestion.solutions.get(*solution)?.clone();
+        let mut placeholders = placeholders.iter();
+        for replacement in &mut solution.replacements {
+            let mut filled = String::new();
+            let mut rest = replacement.replacement.as_str();
+            for placeholder in find_placeholders(&replacement.replacement) {
+                let Some((before, after)) = rest.split_once(placeholder) else {
+                    break;
+                };
+                filled.push_str(before);
+                match placeholders.next() {
+                    Some(answer) if !answer.is_empty() => filled.push_str(answer),
+                    _ => filled.push_str(placeholder),
+                }
+                rest = after;
+            }
+            filled.push_str(rest);
+            replacement.replacement = filled;
+        }
+
+        Some(Suggestion {
+            message: suggestion.message.clone(),
+            snippets: suggestion.snippets.clone(),
+            solutions: vec![solution],
+        })
+    }
+}
+
+fn has_placeholders(candidate: &Candidate<'_>, solution: &Solution) -> bool {
+    candidate.applicability == Applicability::HasPlaceholders
+        && solution
+            .replacements
+            .iter()
+            .any(|replacement| find_placeholders(&replacement.replacement).next().is_some())
+}
+
+/// Finds the `/* ... */` placeholders rustc leaves for the user to fill in.
+fn find_placeholders(replacement: &str) -> impl Iterator<Item = &str> {
+    let mut rest = replacement;
+    std::iter::from_fn(move || {
+        let start = rest.find("/*")?;
+        let end = start + rest[start..].find("*/")? + 2;
+        let placeholder = &rest[start..end];
+        rest = &rest[end..];
+        Some(placeholder)
+    })
+}
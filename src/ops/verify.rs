@@ -0,0 +1,47 @@
+use std::process::Command;
+
+use crate::core::{interrupt, sandbox::Sandbox};
+use crate::CargoResult;
+
+/// A command that must still succeed once the fixes are applied, like `cargo test`.
+#[derive(Debug, Clone)]
+pub(crate) struct VerifyCommand {
+    line: String,
+    program: String,
+    args: Vec<String>,
+}
+
+impl VerifyCommand {
+    /// Splits `line` into words the way a POSIX shell would, without running one.
+    pub(crate) fn parse(line: &str) -> Result<Self, String> {
+        let words = shlex::split(line).ok_or_else(|| "unterminated quote".to_owned())?;
+        let Some((program, args)) = words.split_first() else {
+            return Err("no command given".to_owned());
+        };
+        Ok(Self {
+            line: line.to_owned(),
+            program: program.clone(),
+            args: args.to_vec(),
+        })
+    }
+
+    /// Runs the command, returning whether it succeeded.
+    ///
+    /// Its output goes to stderr, leaving stdout to `--message-format json`.
+    pub(crate) fn run(&self, sandbox: Option<&Sandbox>) -> CargoResult<bool> {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).stdout(std::io::stderr());
+        if let Some(sandbox) = sandbox {
+            sandbox.configure(&mut command);
+        }
+        let output = interrupt::output(&mut command)
+            .map_err(|error| error.context(format!("failed to run `{}`", self.line)))?;
+        Ok(output.status.success())
+    }
+}
+
+impl std::fmt::Display for VerifyCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.line)
+    }
+}
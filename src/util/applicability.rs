@@ -0,0 +1,81 @@
+use std::fmt;
+
+use rustfix::diagnostics::Diagnostic;
+
+/// How confident the compiler must be in a suggestion for it to be applied.
+///
+/// Each level includes all of the levels before it.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    clap::ValueEnum,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Applicability {
+    /// Only suggestions that are definitely correct
+    #[default]
+    Machine,
+    /// Also suggestions that may change the meaning of the code
+    MaybeIncorrect,
+    /// Also suggestions that leave placeholders to fill in
+    HasPlaceholders,
+    /// Also suggestions of unknown quality
+    Unspecified,
+}
+
+impl Applicability {
+    fn from_diagnostic(applicability: Option<&rustfix::diagnostics::Applicability>) -> Self {
+        match applicability {
+            Some(rustfix::diagnostics::Applicability::MachineApplicable) => Self::Machine,
+            Some(rustfix::diagnostics::Applicability::MaybeIncorrect) => Self::MaybeIncorrect,
+            Some(rustfix::diagnostics::Applicability::HasPlaceholders) => Self::HasPlaceholders,
+            Some(rustfix::diagnostics::Applicability::Unspecified) | None => Self::Unspecified,
+        }
+    }
+
+    /// Drops the replacements in `diagnostic` that are riskier than `self`.
+    ///
+    /// Returns the riskiest applicability among the replacements that remain.
+    pub(crate) fn restrict(self, diagnostic: &mut Diagnostic) -> Option<Self> {
+        let mut riskiest = None;
+        for span in diagnostic
+            .children
+            .iter_mut()
+            .flat_map(|child| child.spans.iter_mut())
+        {
+            if span.suggested_replacement.is_none() {
+                continue;
+            }
+            let applicability = Self::from_diagnostic(span.suggestion_applicability.as_ref());
+            if self < applicability {
+                span.suggested_replacement = None;
+            } else {
+                riskiest = riskiest.max(Some(applicability));
+            }
+        }
+        riskiest
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Machine => "machine",
+            Self::MaybeIncorrect => "maybe-incorrect",
+            Self::HasPlaceholders => "has-placeholders",
+            Self::Unspecified => "unspecified",
+        }
+    }
+}
+
+impl fmt::Display for Applicability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
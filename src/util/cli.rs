@@ -1,20 +1,27 @@
+use std::path::Path;
+
 use clap::Parser;
 
+use crate::core::sysroot::{get_host_tuple, is_target_installed};
+use crate::util::features::{FeatureMatrix, FeatureSet};
 use crate::CargoResult;
 
-#[derive(Debug, Parser)]
+/// How many features `--feature-powerset` combines at most, unless told otherwise.
+pub(crate) const DEFAULT_DEPTH: usize = 2;
+
+#[derive(Debug, Clone, Default, Parser)]
 pub struct CheckFlags {
     /// Package(s) to fix
     #[arg(short, long, value_name = "SPEC", help_heading = "Package Selection")]
-    package: Vec<String>,
+    pub(crate) package: Vec<String>,
 
     /// Fix all packages in the workspace
     #[arg(long, help_heading = "Package Selection")]
-    workspace: bool,
+    pub(crate) workspace: bool,
 
     /// Exclude packages from the fixes
     #[arg(long, value_name = "SPEC", help_heading = "Package Selection")]
-    exclude: Vec<String>,
+    pub(crate) exclude: Vec<String>,
 
     /// Alias for --workspace (deprecated)
     #[arg(long, help_heading = "Package Selection")]
@@ -22,43 +29,43 @@ pub struct CheckFlags {
 
     /// Fix only this package's library
     #[arg(long, help_heading = "Target Selection")]
-    lib: bool,
+    pub(crate) lib: bool,
 
     /// Fix all binaries
     #[arg(long, help_heading = "Target Selection")]
-    bins: bool,
+    pub(crate) bins: bool,
 
     /// Fix only the specified binary
     #[arg(long, value_name = "NAME", help_heading = "Target Selection")]
-    bin: Option<String>,
+    pub(crate) bin: Option<String>,
 
     /// Fix all examples
     #[arg(long, help_heading = "Target Selection")]
-    examples: bool,
+    pub(crate) examples: bool,
 
     /// Fix only the specified binary
     #[arg(long, value_name = "NAME", help_heading = "Target Selection")]
-    example: Option<String>,
+    pub(crate) example: Option<String>,
 
     /// Fix all tests
     #[arg(long, help_heading = "Target Selection")]
-    tests: bool,
+    pub(crate) tests: bool,
 
     /// Fix only the specified test
     #[arg(long, value_name = "NAME", help_heading = "Target Selection")]
-    test: Option<String>,
+    pub(crate) test: Option<String>,
 
     /// Fix all benches
     #[arg(long, help_heading = "Target Selection")]
-    benches: bool,
+    pub(crate) benches: bool,
 
     /// Fix only the specified bench
     #[arg(long, value_name = "NAME", help_heading = "Target Selection")]
-    bench: Option<String>,
+    pub(crate) bench: Option<String>,
 
     /// Fix all targets
     #[arg(long, help_heading = "Target Selection")]
-    all_targets: bool,
+    pub(crate) all_targets: bool,
 
     /// Space or comma separated list of features to activate
     #[arg(
@@ -67,15 +74,41 @@ pub struct CheckFlags {
         value_name = "FEATURES",
         help_heading = "Feature Selection"
     )]
-    features: Vec<String>,
+    pub(crate) features: Vec<String>,
 
     /// Activate all available features
     #[arg(long, help_heading = "Feature Selection")]
-    all_features: bool,
+    pub(crate) all_features: bool,
 
     /// Do not activate the `default` feature
     #[arg(long, help_heading = "Feature Selection")]
-    no_default_features: bool,
+    pub(crate) no_default_features: bool,
+
+    /// Fix each package without features, with each feature on its own, and with all of them
+    #[arg(
+        long,
+        help_heading = "Feature Selection",
+        conflicts_with_all = ["features", "all_features", "no_default_features", "commit"]
+    )]
+    pub(crate) each_feature: bool,
+
+    /// Fix each package with every combination of its features
+    #[arg(
+        long,
+        help_heading = "Feature Selection",
+        conflicts_with_all = ["features", "all_features", "no_default_features", "commit", "each_feature"]
+    )]
+    pub(crate) feature_powerset: bool,
+
+    /// Combine at most this many features with `--feature-powerset`
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_DEPTH,
+        requires = "feature_powerset",
+        help_heading = "Feature Selection"
+    )]
+    pub(crate) depth: usize,
 
     /// Unstable (nightly-only) flags
     #[arg(short = 'Z', value_name = "FLAG")]
@@ -97,7 +130,7 @@ pub struct CheckFlags {
     )]
     profile: Option<String>,
 
-    /// Fix for the target triple
+    /// Fix for the target triple, which may be given more than once
     #[arg(long, value_name = "TRIPLE", help_heading = "Compilation Options")]
     target: Vec<String>,
 
@@ -107,7 +140,7 @@ pub struct CheckFlags {
 
     /// Path to Cargo.toml
     #[arg(long, value_name = "PATH", help_heading = "Manifest Options")]
-    manifest_path: Option<String>,
+    pub(crate) manifest_path: Option<String>,
 
     /// Path to Cargo.lock (unstable)
     #[arg(long, value_name = "PATH", help_heading = "Manifest Options")]
@@ -153,6 +186,16 @@ impl CheckFlags {
         }
     }
 
+    /// Points an absolute `--manifest-path` inside `from` at the same manifest inside `to`.
+    pub(crate) fn rebase_manifest_path(&mut self, from: &Path, to: &Path) {
+        let Some(manifest_path) = &self.manifest_path else {
+            return;
+        };
+        if let Ok(relative) = Path::new(manifest_path).strip_prefix(from) {
+            self.manifest_path = Some(to.join(relative).display().to_string());
+        }
+    }
+
     /// Whether one of this package's targets is explicitly selected for fixing.
     pub(crate) fn selects_package_targets(
         &self,
@@ -171,6 +214,86 @@ impl CheckFlags {
         Ok(false)
     }
 
+    /// The combinations of features to fix each package with, if more than one was asked for.
+    pub(crate) fn feature_matrix(&self) -> Option<FeatureMatrix> {
+        if self.each_feature {
+            Some(FeatureMatrix::EachFeature)
+        } else if self.feature_powerset {
+            Some(FeatureMatrix::Powerset { depth: self.depth })
+        } else {
+            None
+        }
+    }
+
+    /// These flags narrowed down to `package`, built with `features`.
+    pub(crate) fn for_features(&self, package: &str, features: &FeatureSet) -> Self {
+        let mut flags = self.clone();
+        flags.package = vec![package.to_owned()];
+        flags.workspace = false;
+        flags.all = false;
+        flags.exclude.clear();
+        flags.each_feature = false;
+        flags.feature_powerset = false;
+        match features {
+            FeatureSet::Only(names) => {
+                flags.features.clone_from(names);
+                flags.no_default_features = true;
+            }
+            FeatureSet::All => flags.all_features = true,
+        }
+        flags
+    }
+
+    /// Whether libraries and binaries are also checked as unit tests.
+    pub(crate) fn checks_unit_tests(&self) -> bool {
+        self.tests || self.benches || self.all_targets
+    }
+
+    /// Drops the `--target`s whose standard library is not installed, returning them.
+    ///
+    /// They are all kept when none of them is installed, for Cargo to report the error.
+    pub(crate) fn remove_uninstalled_targets(&mut self) -> Vec<String> {
+        let builds_std = self
+            .unstable_flags
+            .iter()
+            .any(|flag| flag.starts_with("build-std"));
+        if builds_std {
+            return Vec::new();
+        }
+        let (installed, missing): (Vec<_>, Vec<_>) =
+            self.target.iter().cloned().partition(|target| {
+                // Custom targets come without a standard library.
+                target == "host-tuple" || target.ends_with(".json") || is_target_installed(target)
+            });
+        if installed.is_empty() {
+            return Vec::new();
+        }
+        self.target = installed;
+        missing
+    }
+
+    /// The target triples to build for, as named in the target directory.
+    pub(crate) fn platforms(&self) -> Vec<String> {
+        self.target
+            .iter()
+            .map(|target| {
+                if target == "host-tuple" {
+                    if let Some(host) = get_host_tuple() {
+                        return host.clone();
+                    }
+                }
+                // Custom targets are given as the path to their specification.
+                let path = Path::new(target);
+                match path.file_stem() {
+                    Some(stem) if path.extension().is_some_and(|ext| ext == "json") => {
+                        stem.to_string_lossy().into_owned()
+                    }
+                    _ => target.clone(),
+                }
+            })
+            .collect()
+    }
+
     fn has_target_selection(&self) -> bool {
         self.lib
             || self.bins
@@ -0,0 +1,311 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use cargo_metadata::Metadata;
+use serde::Deserialize;
+
+use crate::util::applicability::Applicability;
+use crate::util::lints::LintOpts;
+use crate::CargoResult;
+
+const FILE_NAME: &str = "fixit.toml";
+
+/// Settings shared by everyone working on a workspace, so a bare `cargo fixit` behaves the same
+/// for all of them.
+///
+/// The workspace is configured by `fixit.toml` at its root, or `[workspace.metadata.fixit]`.
+/// A `fixit.toml` in the directory of a member, in one below it, or in one between it and the
+/// root, overrides the lints, applicability and excluded paths for the files below it.
+/// Command-line flags take precedence over all of them.
+#[derive(Debug, Default)]
+pub(crate) struct WorkspaceConfig {
+    root: Config,
+    /// The configured subdirectories, relative to the workspace root
+    dirs: Vec<(PathBuf, Config)>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Config {
+    /// Run `clippy` instead of `check`
+    clippy: Option<bool>,
+    /// How many times fixes are applied to a build unit before giving up on it
+    max_iterations: Option<usize>,
+    /// Packages to leave alone unless selected with `--package`
+    #[serde(default)]
+    exclude_packages: Vec<String>,
+    /// Only fix these lints
+    lints: Option<Vec<String>>,
+    /// Do not fix these lints
+    skip_lints: Option<Vec<String>>,
+    applicability: Option<Applicability>,
+    /// Globs of paths to never fix, relative to the directory of the configuration
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+impl WorkspaceConfig {
+    pub(crate) fn load(metadata: &Metadata) -> CargoResult<Self> {
+        let workspace_root = metadata.workspace_root.as_std_path();
+        let root_file = workspace_root.join(FILE_NAME);
+        let from_metadata = metadata.workspace_metadata.get("fixit");
+        let root = match (read(&root_file)?, from_metadata) {
+            (Some(_), Some(_)) => anyhow::bail!(
+                "`{}` and `[workspace.metadata.fixit]` both configure the workspace; \
+                only one of them may be used",
+                root_file.display()
+            ),
+            (Some(config), None) => config,
+            (None, Some(value)) => Config::deserialize(value)
+                .context("failed to parse `[workspace.metadata.fixit]`")?,
+            (None, None) => Config::default(),
+        };
+
+        let mut dirs = Vec::new();
+        for dir in config_dirs(metadata)? {
+            if let Some(config) = read(&workspace_root.join(&dir).join(FILE_NAME))? {
+                dirs.push((dir, config));
+            }
+        }
+        for (dir, config) in &dirs {
+            if let Some(key) = config.workspace_key() {
+                anyhow::bail!(
+                    "`{key}` in `{}` can only be set for the whole workspace",
+                    workspace_root.join(dir).join(FILE_NAME).display()
+                );
+            }
+        }
+        for (dir, config) in std::iter::once((Path::new(""), &root))
+            .chain(dirs.iter().map(|(dir, config)| (dir.as_path(), config)))
+        {
+            for pattern in &config.exclude {
+                glob::Pattern::new(pattern).with_context(|| {
+                    format!(
+                        "failed to parse excluded path `{pattern}` in `{}`",
+                        workspace_root.join(dir).join(FILE_NAME).display()
+                    )
+                })?;
+            }
+        }
+        // Deepest first, so the nearest configuration is found first.
+        dirs.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+
+        Ok(Self { root, dirs })
+    }
+
+    pub(crate) fn clippy(&self) -> bool {
+        self.root.clippy.unwrap_or(false)
+    }
+
+    pub(crate) fn max_iterations(&self) -> Option<usize> {
+        self.root.max_iterations
+    }
+
+    pub(crate) fn exclude_packages(&self) -> &[String] {
+        &self.root.exclude_packages
+    }
+
+    pub(crate) fn applicability(&self) -> Option<Applicability> {
+        self.root.applicability
+    }
+
+    /// The lints selected for the whole workspace, with those given on the command line taking
+    /// precedence.
+    pub(crate) fn lint_opts(&self, cli: &LintOpts) -> LintOpts {
+        merge_lints(cli, [&self.root])
+    }
+
+    /// The directories that override the workspace's settings, deepest first.
+    ///
+    /// Each comes with its lints and applicability, as inherited from the directories above it
+    /// and with the command line taking precedence.
+    pub(crate) fn dir_overrides(
+        &self,
+        cli_lints: &LintOpts,
+        cli_applicability: Option<Applicability>,
+    ) -> Vec<(&Path, LintOpts, Option<Applicability>)> {
+        self.dirs
+            .iter()
+            .filter(|(_, config)| {
+                config.lints.is_some()
+                    || config.skip_lints.is_some()
+                    || config.applicability.is_some()
+            })
+            .map(|(dir, _)| {
+                let configs = self.configs_for(dir);
+                let applicability = cli_applicability
+                    .or_else(|| configs.clone().find_map(|config| config.applicability));
+                (
+                    dir.as_path(),
+                    merge_lints(cli_lints, configs),
+                    applicability,
+                )
+            })
+            .collect()
+    }
+
+    /// The paths that must not be fixed.
+    pub(crate) fn excluded_paths(&self) -> ExcludedPaths {
+        let patterns = std::iter::once((Path::new(""), &self.root))
+            .chain(
+                self.dirs
+                    .iter()
+                    .map(|(dir, config)| (dir.as_path(), config)),
+            )
+            .flat_map(|(dir, config)| {
+                config.exclude.iter().filter_map(move |pattern| {
+                    // Validated when loading.
+                    let pattern = glob::Pattern::new(pattern).ok()?;
+                    Some((dir.to_owned(), pattern))
+                })
+            })
+            .collect();
+        ExcludedPaths { patterns }
+    }
+
+    /// The configurations that apply to `dir`, nearest first.
+    fn configs_for<'a>(&'a self, dir: &'a Path) -> impl Iterator<Item = &'a Config> + Clone {
+        self.dirs
+            .iter()
+            .filter(move |(parent, _)| dir.starts_with(parent))
+            .map(|(_, config)| config)
+            .chain(std::iter::once(&self.root))
+    }
+}
+
+/// Globs of paths to never fix, from the `exclude` settings.
+#[derive(Debug, Default)]
+pub(crate) struct ExcludedPaths {
+    /// Each pattern along with the directory it is relative to
+    patterns: Vec<(PathBuf, glob::Pattern)>,
+}
+
+impl ExcludedPaths {
+    /// Whether a file, relative to the workspace root, was excluded.
+    pub(crate) fn contains(&self, file: &Path) -> bool {
+        self.patterns.iter().any(|(dir, pattern)| {
+            let Ok(relative) = file.strip_prefix(dir) else {
+                return false;
+            };
+            // Excluding a directory excludes everything below it.
+            relative.ancestors().any(|path| pattern.matches_path(path))
+        })
+    }
+}
+
+impl Config {
+    /// A setting that only makes sense for the whole workspace, if one was set.
+    fn workspace_key(&self) -> Option<&'static str> {
+        if self.clippy.is_some() {
+            Some("clippy")
+        } else if self.max_iterations.is_some() {
+            Some("max-iterations")
+        } else if !self.exclude_packages.is_empty() {
+            Some("exclude-packages")
+        } else {
+            None
+        }
+    }
+}
+
+/// Takes each list of lints from the command line, or else the nearest configuration.
+fn merge_lints<'a>(
+    cli: &LintOpts,
+    configs: impl IntoIterator<Item = &'a Config> + Clone,
+) -> LintOpts {
+    let nearest = |list: fn(&Config) -> Option<&Vec<String>>| {
+        configs
+            .clone()
+            .into_iter()
+            .find_map(list)
+            .cloned()
+            .unwrap_or_default()
+    };
+    LintOpts {
+        lints: if cli.lints.is_empty() {
+            nearest(|config| config.lints.as_ref())
+        } else {
+            cli.lints.clone()
+        },
+        skip_lints: if cli.skip_lints.is_empty() {
+            nearest(|config| config.skip_lints.as_ref())
+        } else {
+            cli.skip_lints.clone()
+        },
+    }
+}
+
+fn read(path: &Path) -> CargoResult<Option<Config>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => {
+            return Err(error).with_context(|| format!("failed to read `{}`", path.display()))
+        }
+    };
+    let config = toml_edit::de::from_str(&contents)
+        .with_context(|| format!("failed to parse `{}`", path.display()))?;
+    Ok(Some(config))
+}
+
+/// The directories that may hold a `fixit.toml` besides the workspace root, relative to it.
+///
+/// These are the directories of the workspace members and everything below them, along with
+/// those between them and the root.
+fn config_dirs(metadata: &Metadata) -> CargoResult<BTreeSet<PathBuf>> {
+    let workspace_root = metadata.workspace_root.as_std_path();
+    let target_dir = metadata.target_directory.as_std_path();
+    let member_dirs = metadata
+        .workspace_packages()
+        .into_iter()
+        .filter_map(|package| {
+            let dir = package.manifest_path.parent()?.as_std_path();
+            dir.strip_prefix(workspace_root).ok()
+        })
+        .collect::<BTreeSet<_>>();
+    let mut dirs = member_dirs
+        .iter()
+        .flat_map(|dir| dir.ancestors())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(Path::to_owned)
+        .collect::<BTreeSet<_>>();
+    // Members nested in another are walked along with it.
+    for dir in member_dirs.iter().filter(|dir| {
+        !member_dirs
+            .iter()
+            .any(|other| other != *dir && dir.starts_with(other))
+    }) {
+        subdirs(workspace_root, dir, target_dir, &mut dirs)?;
+    }
+    Ok(dirs)
+}
+
+/// Adds the directories below `dir`, relative to `workspace_root`, to `dirs`.
+///
+/// The target directory and hidden directories, like those of version control, are left out, as
+/// are symlinked ones to avoid cycles.
+fn subdirs(
+    workspace_root: &Path,
+    dir: &Path,
+    target_dir: &Path,
+    dirs: &mut BTreeSet<PathBuf>,
+) -> CargoResult<()> {
+    let path = workspace_root.join(dir);
+    let entries =
+        fs::read_dir(&path).with_context(|| format!("failed to read `{}`", path.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir()
+            || entry.path() == target_dir
+            || entry.file_name().to_string_lossy().starts_with('.')
+        {
+            continue;
+        }
+        let subdir = dir.join(entry.file_name());
+        subdirs(workspace_root, &subdir, target_dir, dirs)?;
+        dirs.insert(subdir);
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use anyhow::Context;
+use cargo_metadata::Edition;
+use cargo_util::paths;
+
+use crate::CargoResult;
+
+/// The edition that packages on `edition` can migrate to, if it is stable.
+pub fn next(edition: Edition) -> Option<Edition> {
+    match edition {
+        Edition::E2015 => Some(Edition::E2018),
+        Edition::E2018 => Some(Edition::E2021),
+        Edition::E2021 => Some(Edition::E2024),
+        _ => None,
+    }
+}
+
+/// The lint group whose fixes migrate code to `edition`.
+pub fn compatibility_lint(edition: Edition) -> Option<&'static str> {
+    match edition {
+        Edition::E2018 => Some("rust-2018-compatibility"),
+        Edition::E2021 => Some("rust-2021-compatibility"),
+        Edition::E2024 => Some("rust-2024-compatibility"),
+        _ => None,
+    }
+}
+
+/// The lint group whose fixes move code on `edition` to its idioms.
+pub fn idioms_lint(edition: Edition) -> Option<&'static str> {
+    match edition {
+        Edition::E2015 => None,
+        _ => Some("rust-2018-idioms"),
+    }
+}
+
+/// Sets the `edition` of a package, following `edition.workspace = true` to the workspace root.
+///
+/// Returns the manifest that was changed.
+pub fn set_edition<'m>(
+    manifest_path: &'m Path,
+    workspace_manifest_path: &'m Path,
+    edition: Edition,
+) -> CargoResult<&'m Path> {
+    let mut manifest = read_manifest(manifest_path)?;
+    // `[project]` is a deprecated alias for `[package]`
+    let package = if manifest.contains_key("package") {
+        manifest.get_mut("package")
+    } else {
+        manifest.get_mut("project")
+    };
+    let package = package
+        .and_then(|package| package.as_table_like_mut())
+        .with_context(|| format!("no `[package]` table in `{}`", manifest_path.display()))?;
+    if !is_inherited(package) {
+        package.insert("edition", toml_edit::value(edition.as_str()));
+        paths::write(manifest_path, manifest.to_string())?;
+        return Ok(manifest_path);
+    }
+
+    let mut manifest = read_manifest(workspace_manifest_path)?;
+    let package = manifest
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.get_mut("package"))
+        .and_then(|package| package.as_table_like_mut())
+        .with_context(|| {
+            format!(
+                "no `[workspace.package]` table in `{}`",
+                workspace_manifest_path.display()
+            )
+        })?;
+    package.insert("edition", toml_edit::value(edition.as_str()));
+    paths::write(workspace_manifest_path, manifest.to_string())?;
+    Ok(workspace_manifest_path)
+}
+
+/// Whether a package takes its `edition` from the workspace with `edition.workspace = true`.
+pub fn inherits_edition(manifest_path: &Path) -> CargoResult<bool> {
+    let manifest = read_manifest(manifest_path)?;
+    let package = manifest
+        .get("package")
+        .or_else(|| manifest.get("project"))
+        .and_then(|package| package.as_table_like());
+    Ok(package.is_some_and(is_inherited))
+}
+
+fn is_inherited(package: &dyn toml_edit::TableLike) -> bool {
+    package
+        .get("edition")
+        .and_then(|edition| edition.as_table_like())
+        .and_then(|edition| edition.get("workspace"))
+        .and_then(|workspace| workspace.as_bool())
+        .unwrap_or(false)
+}
+
+fn read_manifest(path: &Path) -> CargoResult<toml_edit::DocumentMut> {
+    paths::read(path)?
+        .parse()
+        .with_context(|| format!("failed to parse `{}`", path.display()))
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Which combinations of a package's features to fix.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum FeatureMatrix {
+    /// No features, each feature on its own, then all of them
+    EachFeature,
+    /// Every combination of at most `depth` features
+    Powerset { depth: usize },
+}
+
+/// A set of features to check a package with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FeatureSet {
+    /// Exactly these features, without the default ones
+    Only(Vec<String>),
+    All,
+}
+
+impl FeatureMatrix {
+    /// The feature sets to fix a package with, leaving out those enabling the same features as an
+    /// earlier one.
+    pub(crate) fn combinations(self, features: &BTreeMap<String, Vec<String>>) -> Vec<FeatureSet> {
+        let names = features.keys().cloned().collect::<Vec<_>>();
+        let candidates = match self {
+            Self::EachFeature => std::iter::once(FeatureSet::Only(Vec::new()))
+                .chain(
+                    names
+                        .iter()
+                        .map(|name| FeatureSet::Only(vec![name.clone()])),
+                )
+                .chain(std::iter::once(FeatureSet::All))
+                .collect(),
+            Self::Powerset { depth } => {
+                let depth = depth.min(names.len());
+                let mut sets = vec![Vec::new()];
+                for size in 1..=depth {
+                    subsets(&names, size, 0, &mut Vec::new(), &mut sets);
+                }
+                sets.into_iter()
+                    // Enabling a feature along with one that implies it adds nothing.
+                    .filter(|set| {
+                        set.iter().all(|name| {
+                            set.iter().all(|other| {
+                                other == name || !enabled(features, other).contains(name.as_str())
+                            })
+                        })
+                    })
+                    .map(FeatureSet::Only)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let mut seen = BTreeSet::new();
+        candidates
+            .into_iter()
+            .filter(|set| {
+                let enabled = match set {
+                    FeatureSet::Only(names) => names
+                        .iter()
+                        .flat_map(|name| enabled(features, name))
+                        .collect::<BTreeSet<_>>(),
+                    FeatureSet::All => names.iter().map(String::as_str).collect(),
+                };
+                seen.insert(enabled)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for FeatureSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Only(names) if names.is_empty() => write!(f, "no features"),
+            Self::Only(names) => {
+                let label = if names.len() == 1 {
+                    "feature"
+                } else {
+                    "features"
+                };
+                let names = names
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{label} {names}")
+            }
+            Self::All => write!(f, "all features"),
+        }
+    }
+}
+
+/// Pushes every subset of `names[start..]` with `size` more names than `current` to `sets`.
+fn subsets(
+    names: &[String],
+    size: usize,
+    start: usize,
+    current: &mut Vec<String>,
+    sets: &mut Vec<Vec<String>>,
+) {
+    if size == 0 {
+        sets.push(current.clone());
+        return;
+    }
+    for index in start..names.len() {
+        current.push(names[index].clone());
+        subsets(names, size - 1, index + 1, current, sets);
+        current.pop();
+    }
+}
+
+/// The package's own features that enabling `name` turns on, including itself.
+fn enabled<'a>(features: &'a BTreeMap<String, Vec<String>>, name: &str) -> BTreeSet<&'a str> {
+    let mut enabled = BTreeSet::new();
+    let mut pending = features.get_key_value(name).into_iter().collect::<Vec<_>>();
+    while let Some((name, values)) = pending.pop() {
+        if enabled.insert(name.as_str()) {
+            // Values like `dep:serde` and `serde/std` are about dependencies.
+            pending.extend(
+                values
+                    .iter()
+                    .filter_map(|value| features.get_key_value(value)),
+            );
+        }
+    }
+    enabled
+}
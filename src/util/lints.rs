@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::Context;
+use clap::Parser;
+
+use crate::core::sysroot::{clippy_driver, rustc};
+use crate::CargoResult;
+
+#[derive(Clone, Debug, Default, Parser)]
+pub struct LintOpts {
+    /// Only fix these lints (supports lint groups and globs, like `clippy::style`)
+    #[arg(long = "lint", value_name = "LINT", help_heading = "Lint Selection")]
+    pub lints: Vec<String>,
+
+    /// Do not fix these lints (supports lint groups and globs, like `clippy::needless_*`)
+    #[arg(
+        long = "skip-lint",
+        value_name = "LINT",
+        help_heading = "Lint Selection"
+    )]
+    pub skip_lints: Vec<String>,
+}
+
+impl LintOpts {
+    /// Resolves the selected lints, expanding lint groups reported by the compiler.
+    pub fn to_filter(&self, clippy: bool) -> CargoResult<LintFilter> {
+        let needs_groups = self
+            .lints
+            .iter()
+            .chain(&self.skip_lints)
+            .any(|lint| !is_glob(lint));
+        let groups = if needs_groups {
+            lint_groups(clippy)?
+        } else {
+            Default::default()
+        };
+
+        Ok(LintFilter {
+            allow: LintMatcher::new(&self.lints, &groups)?,
+            deny: LintMatcher::new(&self.skip_lints, &groups)?,
+            nothing: false,
+        })
+    }
+}
+
+/// Decides which lints may have their suggestions applied.
+#[derive(Debug, Default)]
+pub struct LintFilter {
+    allow: LintMatcher,
+    deny: LintMatcher,
+    /// Select no lint at all, as an empty allow-list selects every lint
+    nothing: bool,
+}
+
+impl LintFilter {
+    /// A filter that selects no lint.
+    pub(crate) fn nothing() -> Self {
+        Self {
+            nothing: true,
+            ..Self::default()
+        }
+    }
+
+    /// Whether suggestions for a diagnostic with this code should be applied.
+    ///
+    /// Diagnostics without a code can only be fixed when no allow-list was given.
+    pub fn is_selected(&self, code: Option<&str>) -> bool {
+        if self.nothing {
+            return false;
+        }
+        let Some(code) = code else {
+            return self.allow.is_empty();
+        };
+        let code = normalize(code);
+        (self.allow.is_empty() || self.allow.matches(&code)) && !self.deny.matches(&code)
+    }
+}
+
+/// Matches lint names against exact names, lint groups, and glob patterns.
+#[derive(Debug, Default)]
+struct LintMatcher {
+    names: BTreeSet<String>,
+    patterns: Vec<glob::Pattern>,
+}
+
+impl LintMatcher {
+    fn new(raw_lints: &[String], groups: &BTreeMap<String, BTreeSet<String>>) -> CargoResult<Self> {
+        let mut names = BTreeSet::new();
+        let mut patterns = Vec::new();
+
+        for raw_lint in raw_lints {
+            let lint = normalize(raw_lint);
+            if is_glob(&lint) {
+                let pattern = glob::Pattern::new(&lint)
+                    .with_context(|| format!("failed to parse lint pattern `{raw_lint}`"))?;
+                patterns.push(pattern);
+            } else if let Some(members) = groups.get(&lint) {
+                names.extend(members.iter().cloned());
+            } else {
+                names.insert(lint);
+            }
+        }
+
+        Ok(Self { names, patterns })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.patterns.is_empty()
+    }
+
+    fn matches(&self, code: &str) -> bool {
+        self.names.contains(code) || self.patterns.iter().any(|pattern| pattern.matches(code))
+    }
+}
+
+/// Lint names are reported with underscores but are commonly written with dashes.
+fn normalize(lint: &str) -> String {
+    lint.trim().replace('-', "_")
+}
+
+fn is_glob(lint: &str) -> bool {
+    lint.contains(&['*', '?', '['][..])
+}
+
+/// Reads lint group membership from the compiler's `-W help` output.
+fn lint_groups(clippy: bool) -> CargoResult<BTreeMap<String, BTreeSet<String>>> {
+    let program = if clippy {
+        clippy_driver()
+    } else {
+        PathBuf::from(rustc())
+    };
+    let output = Command::new(&program)
+        .args(["-W", "help"])
+        .output()
+        .with_context(|| format!("failed to run `{} -W help`", program.display()))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "failed to list lint groups with `{} -W help`:\n{}",
+            program.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(parse_lint_groups(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_lint_groups(help: &str) -> BTreeMap<String, BTreeSet<String>> {
+    let mut groups = BTreeMap::new();
+    let mut in_groups = false;
+    for line in help.lines() {
+        if line.starts_with("Lint groups") {
+            in_groups = true;
+            continue;
+        } else if !line.starts_with(' ') && !line.is_empty() {
+            in_groups = false;
+            continue;
+        }
+        if !in_groups {
+            continue;
+        }
+
+        let Some((name, members)) = line.trim_start().split_once("  ") else {
+            continue;
+        };
+        let members = members.trim();
+        if name == "name" || name.starts_with('-') {
+            continue;
+        }
+        let members = members
+            .split(", ")
+            .filter(|member| !member.contains(' '))
+            .map(normalize)
+            .collect::<BTreeSet<_>>();
+        if !members.is_empty() {
+            groups.insert(normalize(name), members);
+        }
+    }
+    groups
+}
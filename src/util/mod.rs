@@ -1,4 +1,9 @@
+pub mod applicability;
 pub mod cli;
+pub(crate) mod config;
+pub mod edition;
+pub(crate) mod features;
+pub mod lints;
 pub mod messages;
 pub mod package;
 pub mod vcs;
@@ -1,11 +1,14 @@
-use std::env;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 
 use anyhow::bail;
+use anyhow::Context;
 use clap::Parser;
 
-use crate::CargoResult;
+use crate::{CargoResult, FixitError};
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Default)]
 pub struct VcsOpts {
     /// Fix code even if a VCS was not detected
     #[arg(long)]
@@ -18,49 +21,185 @@ pub struct VcsOpts {
     /// Fix code even if the working directory has staged changes
     #[arg(long)]
     pub allow_staged: bool,
+
+    /// Commit the fixes to git, one commit per group
+    ///
+    /// Whole files are committed, so this requires the working directory to be clean.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "GROUPING",
+        conflicts_with_all = ["dry_run", "allow_dirty", "allow_staged", "staged"]
+    )]
+    pub commit: Option<CommitGrouping>,
+
+    /// Create this branch for the commits
+    #[arg(long, value_name = "NAME", requires = "commit")]
+    pub branch: Option<String>,
+
+    /// Fix only files without uncommitted changes, skipping fixes to the others
+    #[arg(long, conflicts_with = "allow_dirty")]
+    pub skip_dirty: bool,
+
+    /// Only fix code on lines that changed since this git revision
+    #[arg(long, value_name = "REV")]
+    pub since: Option<String>,
+
+    /// Only fix code on lines with staged changes
+    #[arg(long, conflicts_with = "since")]
+    pub staged: bool,
+}
+
+/// How fixes are split into commits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CommitGrouping {
+    /// One commit per lint
+    Lint,
+    /// One commit per package
+    Package,
+    /// One commit for all fixes
+    Single,
 }
 
 impl VcsOpts {
-    pub fn valid_vcs(&self) -> CargoResult<()> {
+    /// Checks the repository `dir` is checked into, unless allowed otherwise.
+    pub fn valid_vcs(&self, dir: &Path) -> CargoResult<()> {
         if self.allow_no_vcs {
             return Ok(());
         }
-        let cwd = env::current_dir()?;
 
-        let repo = git2::Repository::discover(&cwd).ok().filter(|r| {
-            if r.workdir().is_some_and(|workdir| workdir == cwd) {
-                true
-            } else {
-                !r.is_path_ignored(cwd).unwrap_or(false)
-            }
-        });
-
-        let Some(repo) = repo else {
-            bail!(
-                "no VCS found for this package and `cargo fix` can potentially \
-                perform destructive changes; if you'd like to suppress this \
-                error pass `--allow-no-vcs`"
-            );
+        let Some(vcs) = discover(dir) else {
+            return Err(FixitError::NoVcs.into());
         };
 
-        if self.allow_staged && self.allow_dirty {
+        // Staged changes are expected when only fixing them
+        let allow_staged = self.allow_staged || self.staged;
+        if (allow_staged && self.allow_dirty) || self.skip_dirty {
+            return Ok(());
+        }
+        let status = vcs.status()?;
+        let dirty_files = if self.allow_dirty {
+            Vec::new()
+        } else {
+            status.dirty
+        };
+        let staged_files = if allow_staged {
+            Vec::new()
+        } else {
+            status.staged
+        };
+
+        if dirty_files.is_empty() && staged_files.is_empty() {
             return Ok(());
         }
-        let mut dirty_files = Vec::new();
-        let mut staged_files = Vec::new();
 
+        Err(FixitError::VcsDirty {
+            dirty: dirty_files,
+            staged: staged_files,
+        }
+        .into())
+    }
+
+    /// Files that fixes must not be written to, with `--skip-dirty`.
+    pub(crate) fn dirty_files(
+        &self,
+        workspace_root: &Path,
+    ) -> CargoResult<Option<HashSet<PathBuf>>> {
+        if !self.skip_dirty {
+            return Ok(None);
+        }
+        let Some(vcs) = discover(workspace_root) else {
+            bail!("`--skip-dirty` requires a version control system");
+        };
+        let status = vcs.status()?;
+        let staged = if self.allow_staged || self.staged {
+            Vec::new()
+        } else {
+            status.staged
+        };
+        let root = std::path::absolute(vcs.root())?;
+        Ok(Some(
+            status
+                .dirty
+                .into_iter()
+                .chain(staged)
+                .map(|path| root.join(path))
+                .collect(),
+        ))
+    }
+}
+
+/// A version control system the working directory can be checked against.
+trait Vcs {
+    /// The root of the working directory.
+    fn root(&self) -> &Path;
+
+    /// Files with uncommitted changes, relative to the root of the repository.
+    fn status(&self) -> CargoResult<Status>;
+}
+
+#[derive(Debug, Default)]
+struct Status {
+    dirty: Vec<String>,
+    /// Changes that will be part of the next commit, for systems with a staging area
+    staged: Vec<String>,
+}
+
+/// Finds the version control system that `path` is checked into.
+///
+/// When repositories are nested, the innermost one is used.  A Jujutsu repository colocated
+/// with git is checked through Jujutsu.
+fn discover(path: &Path) -> Option<Box<dyn Vcs>> {
+    let git = git2::Repository::discover(path).ok().filter(|r| {
+        if r.workdir().is_some_and(|workdir| workdir == path) {
+            true
+        } else {
+            !r.is_path_ignored(path).unwrap_or(false)
+        }
+    });
+    let other = path.ancestors().find_map(|dir| {
+        let vcs: Box<dyn Vcs> = if dir.join(".jj").is_dir() {
+            Box::new(Jujutsu(dir.to_owned()))
+        } else if dir.join(".hg").is_dir() {
+            Box::new(Mercurial(dir.to_owned()))
+        } else if dir.join(".fslckout").is_file() || dir.join("_FOSSIL_").is_file() {
+            Box::new(Fossil(dir.to_owned()))
+        } else if dir.join(".pijul").is_dir() {
+            Box::new(Pijul(dir.to_owned()))
+        } else {
+            return None;
+        };
+        Some((dir, vcs))
+    });
+    match (git, other) {
+        (Some(git), Some((root, _)))
+            if git
+                .workdir()
+                .is_some_and(|workdir| workdir.starts_with(root) && workdir != root) =>
+        {
+            Some(Box::new(git))
+        }
+        (_, Some((_, other))) => Some(other),
+        (git, None) => git.map(|git| Box::new(git) as Box<dyn Vcs>),
+    }
+}
+
+impl Vcs for git2::Repository {
+    fn root(&self) -> &Path {
+        self.workdir().unwrap_or(self.path())
+    }
+
+    fn status(&self) -> CargoResult<Status> {
+        let mut status = Status::default();
         let mut repo_opts = git2::StatusOptions::new();
         repo_opts.include_ignored(false);
         repo_opts.include_untracked(true);
-        if self.allow_dirty {
-            repo_opts.show(git2::StatusShow::Index);
-        }
-        for status in repo.statuses(Some(&mut repo_opts))?.iter() {
-            if let Some(path) = status.path() {
-                match status.status() {
+        for entry in self.statuses(Some(&mut repo_opts))?.iter() {
+            if let Some(path) = entry.path() {
+                match entry.status() {
                     git2::Status::CURRENT => (),
                     git2::Status::INDEX_NEW
-                        if repo
+                        if self
                             .index()?
                             .get_path(path.as_ref(), 0)
                             .is_some_and(|entry| {
@@ -70,52 +209,379 @@ impl VcsOpts {
                                 .is_intent_to_add()
                             }) =>
                     {
-                        if !self.allow_dirty {
-                            dirty_files.push(path.to_owned());
-                        }
+                        status.dirty.push(path.to_owned());
                     }
                     git2::Status::INDEX_NEW
                     | git2::Status::INDEX_MODIFIED
                     | git2::Status::INDEX_DELETED
                     | git2::Status::INDEX_RENAMED
                     | git2::Status::INDEX_TYPECHANGE => {
-                        if !self.allow_staged {
-                            staged_files.push(path.to_owned());
-                        }
+                        status.staged.push(path.to_owned());
                     }
                     _ => {
-                        if !self.allow_dirty {
-                            dirty_files.push(path.to_owned());
-                        }
+                        status.dirty.push(path.to_owned());
                     }
                 };
             }
         }
+        Ok(status)
+    }
+}
 
-        if dirty_files.is_empty() && staged_files.is_empty() {
-            return Ok(());
+/// A Mercurial repository, by its root.
+struct Mercurial(PathBuf);
+
+impl Vcs for Mercurial {
+    fn root(&self) -> &Path {
+        &self.0
+    }
+
+    fn status(&self) -> CargoResult<Status> {
+        // Each line is a status letter and a path, like `M src/lib.rs`
+        let output = run_vcs(&self.0, "hg", &["status"])?;
+        Ok(Status {
+            dirty: output.lines().filter_map(status_path).collect(),
+            staged: Vec::new(),
+        })
+    }
+}
+
+/// A Jujutsu repository, by its root.
+///
+/// The working copy is itself a commit, so its changes from its parent count as uncommitted.
+struct Jujutsu(PathBuf);
+
+impl Vcs for Jujutsu {
+    fn root(&self) -> &Path {
+        &self.0
+    }
+
+    fn status(&self) -> CargoResult<Status> {
+        let output = run_vcs(&self.0, "jj", &["diff", "--name-only", "--color=never"])?;
+        Ok(Status {
+            dirty: output.lines().map(str::to_owned).collect(),
+            staged: Vec::new(),
+        })
+    }
+}
+
+/// A Fossil checkout, by its root.
+struct Fossil(PathBuf);
+
+impl Vcs for Fossil {
+    fn root(&self) -> &Path {
+        &self.0
+    }
+
+    fn status(&self) -> CargoResult<Status> {
+        // Each line is a change type and a path, like `EDITED     src/lib.rs`
+        let output = run_vcs(&self.0, "fossil", &["changes", "--differ"])?;
+        Ok(Status {
+            dirty: output.lines().filter_map(status_path).collect(),
+            staged: Vec::new(),
+        })
+    }
+}
+
+/// A Pijul repository, by its root.
+struct Pijul(PathBuf);
+
+impl Vcs for Pijul {
+    fn root(&self) -> &Path {
+        &self.0
+    }
+
+    fn status(&self) -> CargoResult<Status> {
+        // Each line is a change type and a path, like `M src/lib.rs`
+        let output = run_vcs(&self.0, "pijul", &["diff", "--short", "--untracked"])?;
+        Ok(Status {
+            dirty: output.lines().filter_map(status_path).collect(),
+            staged: Vec::new(),
+        })
+    }
+}
+
+/// Runs a version control tool in the root of its repository.
+fn run_vcs(root: &Path, program: &str, args: &[&str]) -> CargoResult<String> {
+    let command = format!("{program} {}", args.join(" "));
+    let output = std::process::Command::new(program)
+        .args(args)
+        .current_dir(root)
+        // Mercurial's output without user customizations
+        .env("HGPLAIN", "1")
+        .output()
+        .with_context(|| format!("failed to run `{command}` to check for uncommitted changes"))?;
+    if !output.status.success() {
+        bail!(
+            "failed to run `{command}` to check for uncommitted changes\n{}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// The path of a line of status output that starts with the kind of change.
+fn status_path(line: &str) -> Option<String> {
+    let (_, path) = line.split_once(char::is_whitespace)?;
+    Some(path.trim().to_owned()).filter(|path| !path.is_empty())
+}
+
+/// The lines of each file that changed, to limit fixes to code that is being worked on.
+#[derive(Debug)]
+pub(crate) struct ChangedLines {
+    /// Line ranges, starting at 1, by absolute path
+    files: HashMap<PathBuf, Vec<RangeInclusive<usize>>>,
+}
+
+/// A hunk of a diff without context lines, with 1-based line numbers.
+#[derive(Clone, Copy, Debug)]
+struct Hunk {
+    old_start: usize,
+    old_lines: usize,
+    new_start: usize,
+    new_lines: usize,
+}
+
+impl Hunk {
+    /// The lines of the new file covered by the hunk.
+    ///
+    /// Lines around a removal count as changed.
+    fn new_range(&self) -> RangeInclusive<usize> {
+        if self.new_lines == 0 {
+            self.new_start.max(1)..=self.new_start + 1
+        } else {
+            self.new_start..=self.new_start + self.new_lines - 1
         }
+    }
 
-        let mut files_list = String::new();
-        for file in dirty_files {
-            files_list.push_str("  * ");
-            files_list.push_str(&file);
-            files_list.push_str(" (dirty)\n");
+    /// The line of the old file after the hunk.
+    fn old_end(&self) -> usize {
+        if self.old_lines == 0 {
+            self.old_start + 1
+        } else {
+            self.old_start + self.old_lines
         }
-        for file in staged_files {
-            files_list.push_str("  * ");
-            files_list.push_str(&file);
-            files_list.push_str(" (staged)\n");
+    }
+}
+
+impl ChangedLines {
+    /// Finds the lines changed since `--since`, or staged with `--staged`.
+    ///
+    /// Returns `None` when fixes aren't limited to changed lines.
+    pub(crate) fn from_opts(opts: &VcsOpts, workspace_root: &Path) -> CargoResult<Option<Self>> {
+        if opts.since.is_none() && !opts.staged {
+            return Ok(None);
         }
+        let repo = git2::Repository::discover(workspace_root)
+            .context("`--since` and `--staged` require a git repository")?;
+        let Some(workdir) = repo.workdir() else {
+            bail!("`--since` and `--staged` require a git repository with a working directory");
+        };
+        let mut diff_opts = git2::DiffOptions::new();
+        diff_opts
+            .context_lines(0)
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
 
-        bail!(
-            "the working directory of this package has uncommitted changes, and \
-            `cargo fix` can potentially perform destructive changes; if you'd \
-            like to suppress this error pass `--allow-dirty`, \
-            or commit the changes to these files:\n\
-            \n\
-            {files_list}\n\
-            "
-        );
+        let changed: HashMap<PathBuf, Vec<RangeInclusive<usize>>> = match &opts.since {
+            Some(rev) => {
+                let tree = repo
+                    .revparse_single(rev)
+                    .and_then(|object| object.peel_to_tree())
+                    .with_context(|| format!("failed to find git revision `{rev}`"))?;
+                diff_hunks(
+                    &repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut diff_opts))?,
+                )?
+                .into_iter()
+                .map(|(path, hunks)| (path, hunks.iter().map(Hunk::new_range).collect()))
+                .collect()
+            }
+            None => {
+                let head = repo.head().and_then(|head| head.peel_to_tree()).ok();
+                let staged = diff_hunks(&repo.diff_tree_to_index(
+                    head.as_ref(),
+                    None,
+                    Some(&mut diff_opts),
+                )?)?;
+                // Line numbers reported by the compiler are for the working directory
+                let unstaged =
+                    diff_hunks(&repo.diff_index_to_workdir(None, Some(&mut diff_opts))?)?;
+                staged
+                    .into_iter()
+                    .map(|(path, hunks)| {
+                        let edits = unstaged.get(&path).map(Vec::as_slice).unwrap_or_default();
+                        let hunks = hunks
+                            .into_iter()
+                            .map(|hunk| translate(hunk.new_range(), edits))
+                            .collect();
+                        (path, hunks)
+                    })
+                    .collect()
+            }
+        };
+        let workdir = std::path::absolute(workdir)?;
+        let files = changed
+            .into_iter()
+            .map(|(path, ranges)| (workdir.join(path), ranges))
+            .collect();
+        Ok(Some(Self { files }))
+    }
+
+    /// Whether any of `lines` in `file` changed.
+    pub(crate) fn contains(&self, file: &Path, lines: RangeInclusive<usize>) -> bool {
+        let Ok(file) = std::path::absolute(file) else {
+            return false;
+        };
+        self.files.get(&file).is_some_and(|ranges| {
+            ranges
+                .iter()
+                .any(|range| range.start() <= lines.end() && lines.start() <= range.end())
+        })
+    }
+}
+
+fn diff_hunks(diff: &git2::Diff<'_>) -> CargoResult<HashMap<PathBuf, Vec<Hunk>>> {
+    let mut hunks: HashMap<PathBuf, Vec<Hunk>> = HashMap::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        Some(&mut |delta, hunk| {
+            if let Some(path) = delta.new_file().path() {
+                hunks.entry(path.to_owned()).or_default().push(Hunk {
+                    old_start: hunk.old_start() as usize,
+                    old_lines: hunk.old_lines() as usize,
+                    new_start: hunk.new_start() as usize,
+                    new_lines: hunk.new_lines() as usize,
+                });
+            }
+            true
+        }),
+        None,
+    )?;
+    Ok(hunks)
+}
+
+/// Moves a range of lines in the old file to where it is in the new one, given the `edits`
+/// between them.
+fn translate(lines: RangeInclusive<usize>, edits: &[Hunk]) -> RangeInclusive<usize> {
+    let line = |line: usize, end: bool| {
+        let mut shift = 0isize;
+        for edit in edits {
+            if edit.old_lines != 0 && (edit.old_start..edit.old_end()).contains(&line) {
+                // Edited again since, so it covers the whole edit
+                let range = edit.new_range();
+                return if end { *range.end() } else { *range.start() };
+            }
+            if edit.old_end() <= line {
+                shift += edit.new_lines as isize - edit.old_lines as isize;
+            }
+        }
+        line.saturating_add_signed(shift)
+    };
+    let start = line(*lines.start(), false);
+    let end = line(*lines.end(), true);
+    start..=end.max(start)
+}
+
+/// Commits fixed files to the git repository of the workspace.
+pub(crate) struct Committer {
+    repo: git2::Repository,
+}
+
+impl Committer {
+    pub(crate) fn open(workspace_root: &Path) -> CargoResult<Self> {
+        let repo = git2::Repository::discover(workspace_root)
+            .context("`--commit` requires a git repository")?;
+        if repo.workdir().is_none() {
+            bail!("`--commit` requires a git repository with a working directory");
+        }
+        Ok(Self { repo })
+    }
+
+    /// Creates `name` at `HEAD` and switches to it.
+    ///
+    /// The working directory is left alone, as it already matches `HEAD`.
+    pub(crate) fn create_branch(&self, name: &str) -> CargoResult<()> {
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .context("unable to create a branch without any commits")?;
+        let branch = self
+            .repo
+            .branch(name, &head, false)
+            .with_context(|| format!("failed to create branch `{name}`"))?;
+        let reference = branch
+            .get()
+            .name()
+            .with_context(|| format!("branch `{name}` is not valid UTF-8"))?;
+        self.repo.set_head(reference)?;
+        Ok(())
+    }
+
+    /// Commits the current contents of `files` on top of `HEAD`.
+    ///
+    /// Anything else that is staged is neither committed nor unstaged.
+    pub(crate) fn commit(&self, files: &[&Path], message: &str) -> CargoResult<()> {
+        let workdir = self.repo.workdir().expect("checked on open");
+        let head = self
+            .repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+
+        let mut tree = git2::Index::new()?;
+        if let Some(head) = &head {
+            tree.read_tree(&head.tree()?)?;
+        }
+        let mut index = self.repo.index()?;
+        for file in files {
+            let file = std::path::absolute(file)?;
+            let relative = file
+                .strip_prefix(workdir)
+                .with_context(|| format!("`{}` is outside of the repository", file.display()))?;
+            let id = self.repo.blob_path(&file)?;
+            let mode = tree
+                .get_path(relative, 0)
+                .map(|entry| entry.mode)
+                .unwrap_or(0o100644);
+            let path = relative
+                .to_str()
+                .with_context(|| format!("`{}` is not valid UTF-8", relative.display()))?
+                .replace('\\', "/");
+            tree.add(&git2::IndexEntry {
+                ctime: git2::IndexTime::new(0, 0),
+                mtime: git2::IndexTime::new(0, 0),
+                dev: 0,
+                ino: 0,
+                mode,
+                uid: 0,
+                gid: 0,
+                file_size: 0,
+                id,
+                flags: 0,
+                flags_extended: 0,
+                path: path.into_bytes(),
+            })?;
+            index.add_path(relative)?;
+        }
+        let tree = self.repo.find_tree(tree.write_tree_to(&self.repo)?)?;
+
+        let signature = self
+            .repo
+            .signature()
+            .context("failed to determine the author of the commit")?;
+        let parents = head.iter().collect::<Vec<_>>();
+        self.repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )?;
+        index.write()?;
+        Ok(())
     }
 }
@@ -108,8 +108,9 @@ fn do_not_fix_broken_builds() {
 
     p.cargo_("fix --allow-no-vcs")
         .env("__CARGO_FIX_YOLO", "1")
-        .with_status(101)
+        .with_status(6)
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 error[E0308]: mismatched types
  --> src/lib.rs:8:35
   |
@@ -155,8 +156,11 @@ fn fix_broken_if_requested() {
     p.cargo_("fix --allow-no-vcs --broken-code")
         .env("__CARGO_FIX_YOLO", "1")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (1 fix)
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -209,6 +213,7 @@ fn select_path_dep() {
 [FIXED] bar/src/lib.rs (1 fix)
 [CHECKING] foo v0.1.0
 [FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -269,6 +274,7 @@ fn select_implicit_ignores_path_dep() {
 [CHECKING] bar v0.1.0
 [CHECKING] foo v0.1.0
 [FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -311,6 +317,7 @@ resolver = '2'
 [FIXED] a/build.rs (1 fix)
 [FIXED] a/src/lib.rs (1 fix)
 [FIXED] a/src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -352,6 +359,7 @@ resolver = '2'
 [FIXED] a/build.rs (1 fix)
 [FIXED] a/src/lib.rs (1 fix)
 [FIXED] a/src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -393,6 +401,7 @@ resolver = '2'
 [FIXED] a/build.rs (1 fix)
 [FIXED] a/src/lib.rs (1 fix)
 [FIXED] a/src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -418,6 +427,7 @@ resolver = '2'
 [FIXED] b/src/lib.rs (1 fix)
 [FIXED] c/src/lib.rs (1 fix)
 [FIXED] a/src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -446,6 +456,7 @@ resolver = '2'
 [FIXED] b/src/main.rs (1 fix)
 [FIXED] c/src/main.rs (1 fix)
 [FIXED] a/src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -487,6 +498,7 @@ resolver = '2'
 [FIXED] a/build.rs (1 fix)
 [FIXED] a/src/lib.rs (1 fix)
 [FIXED] a/src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -528,6 +540,7 @@ resolver = '2'
 [FIXED] a/build.rs (1 fix)
 [FIXED] a/src/lib.rs (1 fix)
 [FIXED] a/src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -604,6 +617,7 @@ dep = { path = '../dep' }
 [FIXED] app/build.rs (1 fix)
 [FIXED] app/src/lib.rs (1 fix)
 [FIXED] app/tests/selected.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -644,6 +658,7 @@ resolver = '2'
 
 [FIXED] a/build.rs (1 fix)
 [FIXED] a/src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -685,6 +700,7 @@ resolver = '2'
 [FIXED] a/build.rs (1 fix)
 [FIXED] a/src/lib.rs (1 fix)
 [FIXED] a/src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -733,6 +749,7 @@ resolver = '2'
 [FIXED] a/build.rs (1 fix)
 [FIXED] a/src/lib.rs (1 fix)
 [FIXED] a/src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -832,22 +849,18 @@ fn prepare_for_2018() {
 
     p.cargo_("fix --edition --allow-no-vcs")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (2 fixes)
+[MIGRATING] Cargo.toml from 2015 edition to 2018
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .with_stdout_data("")
-        .with_status(2)
         .run();
 
     println!("{}", p.read_file("src/lib.rs"));
-    assert!(!p.read_file("src/lib.rs").contains("use crate::foo::FOO;"));
-    assert!(!p
+    assert!(p.read_file("src/lib.rs").contains("use crate::foo::FOO;"));
+    assert!(p
         .read_file("src/lib.rs")
         .contains("let x = crate::foo::FOO;"));
 }
@@ -888,22 +901,18 @@ fn fix_tests_with_edition() {
         )
         .build();
 
-    p.cargo_("fix --edition --allow-no-vcs")
+    p.cargo_("fix --edition --allow-no-vcs --all-targets")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+[MIGRATING] Cargo.toml from 2018 edition to 2021
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .with_stdout_data("")
-        .with_status(2)
         .run();
     // Check that the test is fixed.
-    assert!(!p.read_file("src/lib.rs").contains(r#"0..=100 => true,"#));
+    assert!(p.read_file("src/lib.rs").contains(r#"0..=100 => true,"#));
 }
 
 #[cargo_test]
@@ -939,20 +948,17 @@ fn fix_tests_with_edition_idioms() {
         )
         .build();
 
-    p.cargo_("fix --edition-idioms --allow-no-vcs")
+    p.cargo_("fix --edition-idioms --allow-no-vcs --all-targets")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition-idioms' found
-
-Usage: cargo fixit [OPTIONS]
-
-For more information, try '--help'.
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .with_stdout_data("")
-        .with_status(2)
         .run();
     // Check that the test is fixed.
-    assert!(!p.read_file("src/lib.rs").contains("Box<dyn Any>"));
+    assert!(p.read_file("src/lib.rs").contains("Box<dyn Any>"));
 }
 
 #[cargo_test]
@@ -976,21 +982,17 @@ fn local_paths() {
 
     p.cargo_("fix --edition --allow-no-vcs")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[MIGRATING] Cargo.toml from 2015 edition to 2018
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .with_stdout_data("")
-        .with_status(2)
         .run();
 
     println!("{}", p.read_file("src/lib.rs"));
-    assert!(!p.read_file("src/lib.rs").contains("use crate::test::foo;"));
+    assert!(p.read_file("src/lib.rs").contains("use crate::test::foo;"));
 }
 
 #[cargo_test]
@@ -1031,9 +1033,12 @@ fn upgrade_extern_crate() {
     p.cargo_("fix --allow-no-vcs")
         .env("__CARGO_FIX_YOLO", "1")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] bar v0.1.0
 [CHECKING] foo v0.1.0
 [FIXED] src/lib.rs (1 fix)
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .with_stdout_data("")
@@ -1064,17 +1069,13 @@ fn specify_rustflags() {
     p.cargo_("fix --edition --allow-no-vcs")
         .env("RUSTFLAGS", "-C linker=cc")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[MIGRATING] Cargo.toml from 2015 edition to 2018
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .with_stdout_data("")
-        .with_status(2)
         .run();
 }
 
@@ -1108,8 +1109,11 @@ fn fixes_extra_mut() {
     p.cargo_("fix --allow-no-vcs")
         .env("__CARGO_FIX_YOLO", "1")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (1 fix)
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .with_stdout_data("")
@@ -1134,8 +1138,11 @@ fn fixes_two_missing_ampersands() {
     p.cargo_("fix --allow-no-vcs")
         .env("__CARGO_FIX_YOLO", "1")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (2 fixes)
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .with_stdout_data("")
@@ -1159,8 +1166,11 @@ fn tricky() {
     p.cargo_("fix --allow-no-vcs")
         .env("__CARGO_FIX_YOLO", "1")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (2 fixes)
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .with_stdout_data("")
@@ -1181,8 +1191,11 @@ fn preserve_line_endings() {
     p.cargo_("fix --allow-no-vcs")
         .env("__CARGO_FIX_YOLO", "1")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (1 fix)
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -1203,8 +1216,11 @@ fn fix_deny_warnings() {
     p.cargo_("fix --allow-no-vcs")
         .env("__CARGO_FIX_YOLO", "1")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (1 fix)
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -1234,8 +1250,11 @@ fn fix_deny_warnings_but_not_others() {
     p.cargo_("fix --allow-no-vcs")
         .env("__CARGO_FIX_YOLO", "1")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (1 fix)
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -1276,6 +1295,9 @@ fn fix_two_files() {
 [CHECKING] foo v0.0.1
 [FIXED] src/bar.rs (1 fix)
 [FIXED] src/lib.rs (1 fix)
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]]
             .unordered(),
@@ -1370,6 +1392,7 @@ fn fix_features() {
         .with_stderr_data(str![[r#"
 [CHECKING] foo v0.1.0
 [FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -1406,7 +1429,7 @@ fn warns_if_no_vcs_detected() {
     let p = project().file("src/lib.rs", "pub fn foo() {}").build();
 
     p.cargo_("fix")
-        .with_status(101)
+        .with_status(3)
         .with_stderr_data(str![[r#"
 [ERROR] no VCS found for this package and `cargo fix` can potentially perform destructive changes; if you'd like to suppress this error pass `--allow-no-vcs`
 
@@ -1427,7 +1450,7 @@ fn warns_about_dirty_working_directory() {
     p.change_file("src/lib.rs", "");
 
     p.cargo_("fix")
-        .with_status(101)
+        .with_status(4)
         .with_stderr_data(str![[r#"
 [ERROR] the working directory of this package has uncommitted changes, and `cargo fix` can potentially perform destructive changes; if you'd like to suppress this error pass `--allow-dirty`, or commit the changes to these files:
 
@@ -1438,7 +1461,7 @@ fn warns_about_dirty_working_directory() {
 "#]])
         .run();
     p.cargo_("fix --allow-staged")
-        .with_status(101)
+        .with_status(4)
         .with_stderr_contains("  * src/lib.rs (dirty)")
         .run();
     p.cargo_("fix --allow-dirty")
@@ -1457,7 +1480,7 @@ fn warns_about_staged_working_directory() {
     git::add(&repo);
 
     p.cargo_("fix")
-        .with_status(101)
+        .with_status(4)
         .with_stderr_data(str![[r#"
 [ERROR] the working directory of this package has uncommitted changes, and `cargo fix` can potentially perform destructive changes; if you'd like to suppress this error pass `--allow-dirty`, or commit the changes to these files:
 
@@ -1468,7 +1491,7 @@ fn warns_about_staged_working_directory() {
 "#]])
         .run();
     p.cargo_("fix --allow-dirty")
-        .with_status(101)
+        .with_status(4)
         .with_stderr_contains("  * src/lib.rs (staged)")
         .run();
     p.cargo_("fix --allow-staged")
@@ -1494,7 +1517,7 @@ fn warns_about_intent_to_add_working_directory() {
         for args in ["fix", "fix --allow-staged"] {
             project
                 .cargo_(args)
-                .with_status(101)
+                .with_status(4)
                 .with_stderr_contains("  * src/new.rs (dirty)")
                 .run();
         }
@@ -1511,7 +1534,7 @@ fn errors_about_untracked_files() {
     let _ = init(&p.root());
 
     p.cargo_("fix")
-        .with_status(101)
+        .with_status(4)
         .with_stderr_data(str![[r#"
 [ERROR] the working directory of this package has uncommitted changes, and `cargo fix` can potentially perform destructive changes; if you'd like to suppress this error pass `--allow-dirty`, or commit the changes to these files:
 
@@ -1567,8 +1590,11 @@ fn do_not_fix_tests_by_default() {
     p.cargo_("fix --allow-no-vcs")
         .env("__CARGO_FIX_YOLO", "1")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (1 fix)
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -1780,21 +1806,17 @@ fn fix_overlapping() {
 
     p.cargo_("fix --allow-no-vcs --edition --lib")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --allow-no-vcs --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (2 fixes)
+[MIGRATING] Cargo.toml from 2015 edition to 2018
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
-        .with_status(2)
         .run();
 
     let contents = p.read_file("src/lib.rs");
     println!("{}", contents);
-    assert!(!contents.contains("crate::foo::<crate::A>()"));
+    assert!(contents.contains("crate::foo::<crate::A>()"));
 }
 
 #[cargo_test]
@@ -1822,17 +1844,14 @@ fn fix_idioms() {
 
     p.cargo_("fix --edition-idioms --allow-no-vcs")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition-idioms' found
-
-Usage: cargo fixit [OPTIONS]
-
-For more information, try '--help'.
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
-        .with_status(2)
         .run();
 
-    assert!(!p.read_file("src/lib.rs").contains("Box<dyn Any>"));
+    assert!(p.read_file("src/lib.rs").contains("Box<dyn Any>"));
 }
 
 #[cargo_test]
@@ -1840,13 +1859,8 @@ fn idioms_2015_ok() {
     let p = project().file("src/lib.rs", "").build();
 
     p.cargo_("fix --edition-idioms --allow-no-vcs")
-        .with_status(2)
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition-idioms' found
-
-Usage: cargo fixit [OPTIONS]
-
-For more information, try '--help'.
+[CHECKING] foo v0.0.1
 
 "#]])
         .run();
@@ -2013,6 +2027,7 @@ fn doesnt_rebuild_dependencies() {
         .env("__CARGO_FIX_YOLO", "1")
         .with_stdout_data("")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
      Checked foo v0.1.0 - foo (lib)
      Checked bar v0.1.0 - bar (lib)
 [CHECKING] bar v0.1.0
@@ -2025,6 +2040,7 @@ fn doesnt_rebuild_dependencies() {
         .env("__CARGO_FIX_YOLO", "1")
         .with_stdout_data("")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] bar v0.1.0
 [CHECKING] foo v0.1.0
 
@@ -2129,16 +2145,12 @@ fn only_warn_for_relevant_crates() {
 
     p.cargo_("fix --allow-no-vcs --edition")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --allow-no-vcs --version
-
-For more information, try '--help'.
+[CHECKING] a v0.1.0
+[CHECKING] foo v0.1.0
+[MIGRATING] Cargo.toml from 2015 edition to 2018
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
-        .with_status(2)
         .run();
 }
 
@@ -2217,6 +2229,7 @@ fn fix_to_broken_code() {
         .with_stderr_data(str![[r#"
 [CHECKING] bar v0.1.0
 [FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -2242,21 +2255,19 @@ fn fix_with_common() {
         .file("tests/common/mod.rs", "pub fn try() {}")
         .build();
 
-    p.cargo_("fix --edition --allow-no-vcs")
-        .with_status(2)
+    p.cargo_("fix --edition --allow-no-vcs --all-targets")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.0.1
+[FIXED] tests/t1.rs (1 fix)
+[FIXED] tests/common/mod.rs (1 fix)
+[FIXED] tests/t2.rs (1 fix)
+[MIGRATING] Cargo.toml from 2015 edition to 2018
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
 
-    assert_e2e().eq(p.read_file("tests/common/mod.rs"), str!["pub fn try() {}"]);
+    assert_e2e().eq(p.read_file("tests/common/mod.rs"), str!["pub fn r#try() {}"]);
 }
 
 #[cargo_test]
@@ -2284,7 +2295,7 @@ fn fix_in_existing_repo_weird_ignore() {
 [ERROR] no VCS found for this package and `cargo fix` can potentially perform destructive changes; if you'd like to suppress this error pass `--allow-no-vcs`
 
 "#]])
-        .with_status(101)
+        .with_status(3)
         .run();
     p.cargo_("fix").cwd("src").run();
 }
@@ -2304,7 +2315,7 @@ fn fix_color_message() {
 ...
 ",
         )
-        .with_status(101)
+        .with_status(6)
         .run();
 
     p.cargo_("fix --allow-no-vcs --color=never")
@@ -2314,7 +2325,7 @@ fn fix_color_message() {
 ...
 "#]])
         .with_stderr_does_not_contain("[..]\x1b[[..]")
-        .with_status(101)
+        .with_status(6)
         .run();
 }
 
@@ -2362,18 +2373,15 @@ fn edition_v2_resolver_report() {
     p.cargo_("fix --edition --allow-no-vcs")
         .with_stderr_data(
             str![[r#"
+[CHECKING] bar v1.0.0
+[CHECKING] common v1.0.0
+[CHECKING] opt_dep v1.0.0
+[CHECKING] foo v0.1.0
+[MIGRATING] Cargo.toml from 2018 edition to 2021
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
-
-
-[ERROR] unexpected argument '--edition' found
-  tip: a similar argument exists: '--version'
-Usage: cargo fixit --version
-For more information, try '--help'.
-
-"#]]
-            .unordered(),
+"#]],
         )
-        .with_status(2)
         .run();
 }
 
@@ -2397,6 +2405,7 @@ fn rustfix_handles_multi_spans() {
         .with_stderr_data(str![[r#"
 [CHECKING] foo v0.1.0
 [FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -2433,18 +2442,14 @@ fn fix_edition_2021() {
         .build();
     p.cargo_("fix --edition --allow-no-vcs")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+[MIGRATING] Cargo.toml from 2018 edition to 2021
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
-        .with_status(2)
         .run();
-    assert!(!p.read_file("src/lib.rs").contains(r#"0..=100 => true,"#));
+    assert!(p.read_file("src/lib.rs").contains(r#"0..=100 => true,"#));
 }
 
 #[cargo_test]
@@ -2483,6 +2488,9 @@ fn fix_shared_cross_workspace() {
 [CHECKING] [..] v0.1.0
 [CHECKING] [..] v0.1.0
 [FIXED] [..]foo/src/shared.rs (2 fixes)
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]]
             .unordered(),
@@ -2503,6 +2511,9 @@ fn abnormal_exit() {
     // - First run (collect diagnostics pass): writes a file, exits normally.
     // - Second run (verify diagnostics work): it detects the presence of the
     //   file, removes the file, and aborts the process.
+    //
+    // Checking without the fix succeeds, so the fix gets dropped, and then the
+    // check after that aborts again with no fixes left to blame.
     let p = project()
         .file(
             "Cargo.toml",
@@ -2564,32 +2575,15 @@ fn abnormal_exit() {
             "ONCE_PATH",
             paths::root().join("proc-macro-run-once").to_str().unwrap(),
         )
-        // "signal: 6, SIGABRT: process abort signal" on some platforms
         .with_stderr_data(str![[r#"
 [CHECKING] pm v0.1.0
 [CHECKING] foo v0.1.0
-[NOTE] reverting `src/lib.rs` to its original state
-[WARNING] failed to automatically apply fixes suggested by rustc
-
-after fixes were automatically applied the compiler reported errors within these files:
-
-  * src/lib.rs
-
-This likely indicates a bug in either rustc or cargo itself,
-and we would appreciate a bug report! You're likely to see
-a number of compiler warnings after this message which cargo
-attempted to fix but failed. If you could open an issue at
-https://github.com/rust-lang/rust/issues
-quoting the full output of this command we'd be very appreciative!
-Note that you may be able to make some more progress in the near-term
-fixing code with the `--broken-code` flag
-
-The original errors are:
-[WARNING] variable does not need to be mutable
-...
+[WARNING] dropped `unused_variables` fix at src/lib.rs:3:25 as it broke compilation: unused variable: `x`
+[NOTE] try using `--broken-code` to fix errors
+[ERROR] could not compile
 
 "#]])
-        .with_status(101)
+        .with_status(6)
         .run();
 }
 
@@ -2670,16 +2664,20 @@ fn non_edition_lint_migration() {
         .build();
     // Check that it complains about an unused import.
     p.cargo_("check --lib").run();
-    p.cargo_("fix --edition --allow-no-vcs")
-        .with_status(2)
+    p.cargo_("fix --edition --allow-no-vcs --all-targets")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
+[CHECKING] foo v0.1.0
+[WARNING] unused import: `std::str::from_utf8`
+ --> src/lib.rs:6:21
+  |
+6 |                 use std::str::from_utf8;
+  |                     ^^^^^^^^^^^^^^^^^^^
+  |
+  = [NOTE] `#[warn(unused_imports)]` (part of `#[warn(unused)]`) on by default
 
-For more information, try '--help'.
+[FIXED] src/lib.rs (1 fix)
+[MIGRATING] Cargo.toml from 2015 edition to 2018
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -2687,7 +2685,7 @@ For more information, try '--help'.
     // Check it does not remove the "unused" import.
     assert!(contents.contains("use std::str::from_utf8;"));
     // Check that it made the edition migration.
-    assert!(!contents.contains("from_utf8(crate::foo::FOO)"));
+    assert!(contents.contains("from_utf8(crate::foo::FOO)"));
 }
 
 #[cargo_test]
@@ -3178,6 +3176,7 @@ fn fix_in_rust_src() {
         .env("__CARGO_FIX_YOLO", "1")
         .env("RUSTC", &rustc_bin)
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] foo v0.0.0
 error[E0308]: mismatched types
  --> lib.rs:5:9
@@ -3237,8 +3236,12 @@ fn main() {
     p.cargo_("fix --allow-no-vcs")
         .env("__CARGO_FIX_YOLO", "1")
         .with_stderr_data(str![[r#"
+[WARNING] `__CARGO_FIX_YOLO` is deprecated, pass `--applicability unspecified` instead
 [CHECKING] foo v0.0.1
 [FIXED] src/main.rs (1 fix)
+[WARNING] `src/main.rs` has fixes that are not machine-applicable and should be reviewed
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -3282,16 +3285,11 @@ edition = "2021"
 
     p.cargo_("fix --edition --allow-no-vcs")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.0.0
+[MIGRATING] Cargo.toml from 2021 edition to 2024
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
-        .with_status(2)
         .run();
     assert_e2e().eq(
         p.read_file("Cargo.toml"),
@@ -3301,7 +3299,7 @@ For more information, try '--help'.
 [ project ] # After project header
 # After project header line
 name = "foo"
-edition = "2021"
+edition = "2024"
 # After project table
 
 "#]],
@@ -3334,16 +3332,11 @@ edition = "2021"
 
     p.cargo_("fix --edition --allow-no-vcs")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.0.0
+[MIGRATING] Cargo.toml from 2021 edition to 2024
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
-        .with_status(2)
         .run();
     assert_e2e().eq(
         p.read_file("Cargo.toml"),
@@ -3353,7 +3346,7 @@ For more information, try '--help'.
 [ package ] # After package header
 # After package header line
 name = "foo"
-edition = "2021"
+edition = "2024"
 # After package table
 
 # Before project
@@ -3510,16 +3503,11 @@ a = {path = "a", default_features = false}
 
     p.cargo_("fix --edition --allow-no-vcs")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.0.0
+[MIGRATING] Cargo.toml from 2021 edition to 2024
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
-        .with_status(2)
         .run();
     assert_e2e().eq(
         p.read_file("Cargo.toml"),
@@ -3532,7 +3520,7 @@ a = {path = "a", default_features = false}  # After default_features value
 
 [package]
 name = "foo"
-edition = "2021"
+edition = "2024"
 
 [lib]
 name = "foo"
@@ -3614,16 +3602,11 @@ edition = "2021"
 
     p.cargo_("fix --edition --allow-no-vcs")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '--edition' found
-
-  tip: a similar argument exists: '--version'
-
-Usage: cargo fixit --version
-
-For more information, try '--help'.
+[CHECKING] foo v0.0.0
+[MIGRATING] foo/Cargo.toml from 2021 edition to 2024
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
-        .with_status(2)
         .run();
     assert_e2e().eq(
         p.read_file("Cargo.toml"),
@@ -3646,7 +3629,7 @@ a = {path = "a", default_features = false}  # After default_features value
 
 [package]
 name = "foo"
-edition = "2021"
+edition = "2024"
 
 "#]],
     );
@@ -3746,22 +3729,29 @@ dep_df_false = { version = "0.1.0", default-features = false }
     p.cargo_("fix --all --edition --allow-no-vcs")
         .with_stderr_data(
             str![[r#"
+[CHECKING] dep_df_false v0.1.0
+[CHECKING] dep_df_true v0.1.0
+[CHECKING] dep_simple v0.1.0
+[CHECKING] pkg_default v0.1.0
+[CHECKING] pkg_df_false v0.1.0
+[CHECKING] pkg_df_true v0.1.0
+[MIGRATING] pkg_default/Cargo.toml from 2021 edition to 2024
+[MIGRATING] pkg_df_true/Cargo.toml from 2021 edition to 2024
+[MIGRATING] pkg_df_false/Cargo.toml from 2021 edition to 2024
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
-
-For more information, try '--help'.
-
-[ERROR] unexpected argument '--edition' found
-  tip: a similar argument exists: '--version'
-Usage: cargo fixit --all --version
-
-"#]]
-            .unordered(),
+"#]],
         )
-        .with_status(2)
         .run();
 
-    assert_e2e().eq(p.read_file("pkg_default/Cargo.toml"), pkg_default);
-    assert_e2e().eq(p.read_file("pkg_df_true/Cargo.toml"), pkg_df_true);
+    assert_e2e().eq(
+        p.read_file("pkg_default/Cargo.toml"),
+        pkg_default.replace(r#"edition = "2021""#, r#"edition = "2024""#),
+    );
+    assert_e2e().eq(
+        p.read_file("pkg_df_true/Cargo.toml"),
+        pkg_df_true.replace(r#"edition = "2021""#, r#"edition = "2024""#),
+    );
     assert_e2e().eq(
         p.read_file("pkg_df_false/Cargo.toml"),
         str![[r#"
@@ -3769,7 +3759,7 @@ Usage: cargo fixit --all --version
 [package]
 name = "pkg_df_false"
 version = "0.1.0"
-edition = "2021"
+edition = "2024"
 
 [dependencies]
 dep_simple = { workspace = true, default-features = false }
@@ -3792,7 +3782,7 @@ dep_df_false = { workspace = true, default-features = false }
 
 #[cargo_test]
 fn fix_edition_skips_old_editions() {
-    // Checks that -Zfix-edition will skip things that are not 2024.
+    // Packages already on the latest edition are skipped.
     let p = project()
         .file(
             "Cargo.toml",
@@ -3818,51 +3808,181 @@ fn fix_edition_skips_old_editions() {
                 edition = "2024"
             "#,
         )
-        .file("e2024/src/lib.rs", "")
+        .file(
+            "e2024/src/lib.rs",
+            "pub fn foo() -> i32 { let mut x = 1; x }\n",
+        )
         .build();
 
-    // Doing the whole workspace should skip since there is a 2021 in the mix.
-    p.cargo_("fix -Zfix-edition=start=2024 -v")
-        .masquerade_as_nightly_cargo(&["fix-edition"])
+    // Doing an individual package on the latest edition does nothing.
+    p.cargo_("fix --edition --allow-no-vcs -p e2024")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '-v' found
-
-Usage: cargo fixit [OPTIONS]
+[WARNING] `e2024` is already on the latest edition (2024), unable to migrate further
+[CHECKING] e2024 v0.0.0
+[WARNING] variable does not need to be mutable
+ --> e2024/src/lib.rs:1:27
+  |
+1 | pub fn foo() -> i32 { let mut x = 1; x }
+  |                           ----^
+  |                           |
+  |                           [HELP] remove this `mut`
+  |
+  = [NOTE] `#[warn(unused_mut)]` (part of `#[warn(unused)]`) on by default
 
-For more information, try '--help'.
 
 "#]])
-        .with_status(2)
         .run();
+    assert!(p.read_file("e2021/Cargo.toml").contains(r#"edition = "2021""#));
+    assert!(p.read_file("e2024/src/lib.rs").contains("let mut x"));
 
-    // Same with `end`.
-    p.cargo_("fix -Zfix-edition=end=2024,future -v")
-        .masquerade_as_nightly_cargo(&["fix-edition"])
+    // Doing the whole workspace only migrates the older package.
+    p.cargo_("fix --edition --allow-no-vcs --workspace")
         .with_stderr_data(str![[r#"
-[ERROR] unexpected argument '-v' found
+[WARNING] `e2024` is already on the latest edition (2024), unable to migrate further
+[CHECKING] e2021 v0.0.0
+[CHECKING] e2024 v0.0.0
+[WARNING] variable does not need to be mutable
+ --> e2024/src/lib.rs:1:27
+  |
+1 | pub fn foo() -> i32 { let mut x = 1; x }
+  |                           ----^
+  |                           |
+  |                           [HELP] remove this `mut`
+  |
+  = [NOTE] `#[warn(unused_mut)]` (part of `#[warn(unused)]`) on by default
 
-Usage: cargo fixit [OPTIONS]
+[MIGRATING] e2021/Cargo.toml from 2021 edition to 2024
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
-For more information, try '--help'.
+"#]])
+        .run();
+    assert!(p.read_file("e2021/Cargo.toml").contains(r#"edition = "2024""#));
+    assert!(p.read_file("e2024/Cargo.toml").contains(r#"edition = "2024""#));
+    // Warnings unrelated to the migration are left alone.
+    assert!(p.read_file("e2024/src/lib.rs").contains("let mut x"));
+}
+
+#[cargo_test]
+fn fix_edition_lints_per_package() {
+    // Each package is only fixed for the edition it migrates to.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"[workspace]
+            members = ["e2018", "e2021"]
+            resolver = "2"
+            "#,
+        )
+        .file(
+            "e2018/Cargo.toml",
+            r#"
+            [package]
+            name = "e2018"
+            edition = "2018"
+            "#,
+        )
+        .file("e2018/src/lib.rs", "extern \"C\" {\n    pub fn a();\n}\n")
+        .file(
+            "e2021/Cargo.toml",
+            r#"
+            [package]
+            name = "e2021"
+            edition = "2021"
+            "#,
+        )
+        .file("e2021/src/lib.rs", "extern \"C\" {\n    pub fn b();\n}\n")
+        .build();
+
+    p.cargo_("fix --edition --allow-no-vcs --workspace")
+        .with_stderr_data(str![[r#"
+[CHECKING] e2018 v0.0.0
+[CHECKING] e2021 v0.0.0
+[FIXED] e2021/src/lib.rs (1 fix)
+[MIGRATING] e2018/Cargo.toml from 2018 edition to 2021
+[MIGRATING] e2021/Cargo.toml from 2021 edition to 2024
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
-        .with_status(2)
         .run();
+    assert_e2e().eq(
+        p.read_file("e2018/src/lib.rs"),
+        str![[r#"
+extern "C" {
+    pub fn a();
+}
 
-    // Doing an individual package at the correct edition should check it.
-    p.cargo_("fix -Zfix-edition=start=2024 -p e2024")
-        .masquerade_as_nightly_cargo(&["fix-edition"])
+"#]],
+    );
+    assert_e2e().eq(
+        p.read_file("e2021/src/lib.rs"),
+        str![[r#"
+unsafe extern "C" {
+    pub fn b();
+}
+
+"#]],
+    );
+}
+
+#[cargo_test]
+fn fix_edition_shared_by_workspace() {
+    // The edition of `[workspace.package]` only moves with all the packages inheriting it.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"[workspace]
+            members = ["a", "b"]
+            resolver = "2"
+
+            [workspace.package]
+            edition = "2021"
+            "#,
+        )
+        .file(
+            "a/Cargo.toml",
+            r#"
+            [package]
+            name = "a"
+            edition.workspace = true
+            "#,
+        )
+        .file("a/src/lib.rs", "")
+        .file(
+            "b/Cargo.toml",
+            r#"
+            [package]
+            name = "b"
+            edition.workspace = true
+            "#,
+        )
+        .file("b/src/lib.rs", "")
+        .build();
+
+    p.cargo_("fix --edition --allow-no-vcs -p a")
         .with_stderr_data(str![[r#"
-[ERROR] no VCS found for this package and `cargo fix` can potentially perform destructive changes; if you'd like to suppress this error pass `--allow-no-vcs`
+[ERROR] unable to migrate `a` as it shares the workspace's edition with `b`
+select them as well, or set the edition of `a` in its own manifest
 
 "#]])
         .with_status(101)
         .run();
+    assert!(p.read_file("Cargo.toml").contains(r#"edition = "2021""#));
+
+    p.cargo_("fix --edition --allow-no-vcs --workspace")
+        .with_stderr_data(str![[r#"
+[CHECKING] a v0.0.0
+[CHECKING] b v0.0.0
+[MIGRATING] Cargo.toml from 2021 edition to 2024
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert!(p.read_file("Cargo.toml").contains(r#"edition = "2024""#));
 }
 
-#[cargo_test(nightly, reason = "future edition is always unstable")]
+#[cargo_test]
 fn fix_edition_future() {
-    // Checks that the -Zfix-edition can work for the future.
+    // Future editions are unstable, so migration stops at the latest stable edition.
     let p = project()
         .file(
             "Cargo.toml",
@@ -3874,13 +3994,12 @@ fn fix_edition_future() {
         .file("src/lib.rs", "")
         .build();
 
-    p.cargo_("fix -Zfix-edition=end=2024,future")
-        .masquerade_as_nightly_cargo(&["fix-edition"])
+    p.cargo_("fix --edition --allow-no-vcs")
         .with_stderr_data(str![[r#"
-[ERROR] no VCS found for this package and `cargo fix` can potentially perform destructive changes; if you'd like to suppress this error pass `--allow-no-vcs`
+[WARNING] `foo` is already on the latest edition (2024), unable to migrate further
+[CHECKING] foo v0.0.0
 
 "#]])
-        .with_status(101)
         .run();
     assert_e2e().eq(
         p.read_file("Cargo.toml"),
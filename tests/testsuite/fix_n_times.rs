@@ -42,6 +42,11 @@ enum Step {
     /// The suggested fix is the same as `Step::OneFix`, it just shows up
     /// twice.
     TwoFixOverlapping = b'2',
+    /// Emits two suggested fixes which don't overlap.
+    ///
+    /// The first is the same as `Step::OneFix`, the second changes `fix` in
+    /// the comment to `FIX`.
+    TwoFix = b'3',
     /// Generates a warning without a suggestion.
     Warning = b'w',
     /// Generates an error message with no suggestion.
@@ -170,6 +175,10 @@ fn main() {
             output_suggestion(successful_count + 1);
             output_suggestion(successful_count + 2);
         }
+        b'3' => {
+            output_suggestion(successful_count + 1);
+            output_replacement(successful_count + 2, 3, 6, "FIX");
+        }
         b'w' => {
             output_message("warning", successful_count + 1);
         }
@@ -187,6 +196,11 @@ fn main() {
 }
 
 fn output_suggestion(count: usize) {
+    output_replacement(count, 13, 14, &count.to_string());
+}
+
+fn output_replacement(count: usize, start: usize, end: usize, replacement: &str) {
+    let (column_start, column_end) = (start + 1, end + 1);
     let json = format!(
         r#"{{
             "$message_type": "diagnostic",
@@ -197,19 +211,19 @@ fn output_suggestion(count: usize) {
             [
                 {{
                     "file_name": "src/lib.rs",
-                    "byte_start": 13,
-                    "byte_end": 14,
+                    "byte_start": {start},
+                    "byte_end": {end},
                     "line_start": 1,
                     "line_end": 1,
-                    "column_start": 14,
-                    "column_end": 15,
+                    "column_start": {column_start},
+                    "column_end": {column_end},
                     "is_primary": true,
                     "text":
                     [
                         {{
                             "text": "// fix-count 0",
-                            "highlight_start": 14,
-                            "highlight_end": 15
+                            "highlight_start": {column_start},
+                            "highlight_end": {column_end}
                         }}
                     ],
                     "label": "increase this number",
@@ -228,23 +242,23 @@ fn output_suggestion(count: usize) {
                     [
                         {{
                             "file_name": "src/lib.rs",
-                            "byte_start": 13,
-                            "byte_end": 14,
+                            "byte_start": {start},
+                            "byte_end": {end},
                             "line_start": 1,
                             "line_end": 1,
-                            "column_start": 14,
-                            "column_end": 15,
+                            "column_start": {column_start},
+                            "column_end": {column_end},
                             "is_primary": true,
                             "text":
                             [
                                 {{
                                     "text": "// fix-count 0",
-                                    "highlight_start": 14,
-                                    "highlight_end": 15
+                                    "highlight_start": {column_start},
+                                    "highlight_end": {column_end}
                                 }}
                             ],
                             "label": null,
-                            "suggested_replacement": "{count}",
+                            "suggested_replacement": "{replacement}",
                             "suggestion_applicability": "MachineApplicable",
                             "expansion": null
                         }}
@@ -332,6 +346,7 @@ fn fix_one_suggestion() {
         str![[r#"
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]],
         "// fix-count 1",
@@ -348,6 +363,7 @@ fn fix_one_overlapping() {
         str![[r#"
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]],
         "// fix-count 2",
@@ -375,6 +391,7 @@ rustc fix shim comment 5
 
 rustc fix shim comment 6
 
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]],
         "// fix-count 5",
@@ -385,38 +402,49 @@ rustc fix shim comment 6
 #[cargo_test]
 fn fix_verification_failed() {
     // One suggested fix, with an error in the verification step.
-    // This should cause `cargo fix` to back out the changes.
+    // The code compiles without the fix, so it is dropped and the remaining
+    // fixes are kept.
     expect_fix_runs_rustc_n_times(
-        &[Step::OneFix, Step::Error],
+        &[
+            Step::OneFix,
+            Step::Error,
+            Step::SuccessNoOutput,
+            Step::SuccessNoOutput,
+        ],
         |_execs| {},
         str![[r#"
 [CHECKING] foo v0.0.1
-[NOTE] reverting `src/lib.rs` to its original state
-[WARNING] failed to automatically apply fixes suggested by rustc
-
-after fixes were automatically applied the compiler reported errors within these files:
-
-  * src/lib.rs
-
-This likely indicates a bug in either rustc or cargo itself,
-and we would appreciate a bug report! You're likely to see
-a number of compiler warnings after this message which cargo
-attempted to fix but failed. If you could open an issue at
-https://github.com/rust-lang/rust/issues
-quoting the full output of this command we'd be very appreciative!
-Note that you may be able to make some more progress in the near-term
-fixing code with the `--broken-code` flag
-
-The errors reported are:
-rustc fix shim error count=2
+[WARNING] dropped fix at src/lib.rs:1:14 as it broke compilation: rustc fix shim comment 1
 
+"#]],
+        "// fix-count 0",
+        0,
+    );
+}
 
-[NOTE] try using `--broken-code` to fix errors
-[ERROR] could not compile
+#[cargo_test]
+fn fix_verification_failed_drops_breaking_fix() {
+    // Two suggested fixes, with an error in the verification step.
+    // Each fix is checked on its own to find the one that breaks compilation.
+    expect_fix_runs_rustc_n_times(
+        &[
+            Step::TwoFix,
+            Step::Error,
+            Step::SuccessNoOutput,
+            Step::Error,
+            Step::SuccessNoOutput,
+            Step::SuccessNoOutput,
+        ],
+        |_execs| {},
+        str![[r#"
+[CHECKING] foo v0.0.1
+[WARNING] dropped fix at src/lib.rs:1:4 as it broke compilation: rustc fix shim comment 2
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]],
-        "// fix-count 0",
-        101,
+        "// fix-count 1",
+        0,
     );
 }
 
@@ -425,8 +453,11 @@ fn fix_verification_failed_clippy() {
     // This is the same as `fix_verification_failed_clippy`, except it checks
     // the error message has the customization for the clippy URL and
     // subcommand.
+    //
+    // The code also fails to compile without the fix, so nothing can be
+    // blamed on it and the changes are backed out.
     expect_fix_runs_rustc_n_times(
-        &[Step::OneFix, Step::Error],
+        &[Step::OneFix, Step::Error, Step::Error],
         |execs| {
             execs.env("RUSTC_WORKSPACE_WRAPPER", wrapped_clippy_driver());
         },
@@ -457,7 +488,7 @@ rustc fix shim error count=2
 
 "#]],
         "// fix-count 0",
-        101,
+        7,
     );
 }
 
@@ -492,7 +523,7 @@ rustc fix shim error count=1
 
 "#]],
         "// fix-count 0",
-        101,
+        6,
     );
 }
 
@@ -528,6 +559,7 @@ fn broken_code_one_suggestion() {
 [FIXED] src/lib.rs (1 fix)
 rustc fix shim error count=2
 
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]],
         "// fix-count 1",
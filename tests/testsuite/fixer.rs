@@ -0,0 +1,189 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use cargo_fixit::core::events::{Event, Observer};
+use cargo_fixit::ops::{FixReport, Fixer};
+use cargo_fixit::FixitError;
+use cargo_test_support::basic_manifest;
+use cargo_test_support::cargo_test;
+use cargo_test_support::compare::assert_e2e;
+use cargo_test_support::project;
+use snapbox::str;
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<String>>>);
+
+impl Observer for Recorder {
+    fn on_event(&mut self, event: &Event<'_>) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(serde_json::to_value(event).unwrap()["reason"].to_string());
+    }
+}
+
+#[cargo_test]
+fn reports_fixes() {
+    // Run in-process, so nothing stops Cargo from finding the repository's workspace
+    let manifest = format!("{}\n[workspace]\n", basic_manifest("foo", "0.1.0"));
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file(
+            "src/lib.rs",
+            "pub fn a() -> i32 { let mut x = 1; x }\nfn unused() {}\n",
+        )
+        .build();
+    let recorder = Recorder::default();
+
+    let report: FixReport = Fixer::new()
+        .manifest_path(p.root().join("Cargo.toml"))
+        .allow_no_vcs()
+        .observer(recorder.clone())
+        .fix()
+        .unwrap();
+
+    assert_e2e().eq(
+        p.read_file("src/lib.rs"),
+        str![[r#"
+pub fn a() -> i32 { let x = 1; x }
+fn unused() {}
+
+"#]],
+    );
+    let files = report
+        .files
+        .iter()
+        .map(|file| (file.path.strip_prefix(p.root()).unwrap(), file.fixes))
+        .collect::<Vec<_>>();
+    assert_eq!(files, [(Path::new("src/lib.rs"), 1)]);
+    let remaining = report
+        .remaining
+        .iter()
+        .map(|diagnostic| diagnostic.code.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(remaining, [Some("dead_code")]);
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        [
+            r#""unit-scheduled""#,
+            r#""suggestion-applied""#,
+            r#""unit-finished""#,
+            r#""diagnostic-remaining""#,
+        ]
+    );
+}
+
+#[cargo_test]
+fn reports_typed_errors() {
+    let manifest = format!("{}\n[workspace]\n", basic_manifest("foo", "0.1.0"));
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/lib.rs", "pub fn a() -> i32 { let mut x = 1; y }\n")
+        .build();
+
+    let error = Fixer::new()
+        .manifest_path(p.root().join("Cargo.toml"))
+        .allow_no_vcs()
+        .fix()
+        .unwrap_err();
+
+    let Some(FixitError::CompileFailed { errors }) = error.downcast_ref() else {
+        panic!("unexpected error: {error:?}");
+    };
+    assert!(errors
+        .iter()
+        .any(|error| error.contains("cannot find value `y` in this scope")));
+    assert_eq!(error.downcast_ref::<FixitError>().unwrap().exit_code(), 6);
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let mut x = 1; y }\n"
+    );
+}
+
+#[cargo_test]
+fn checks_vcs_of_workspace() {
+    // The current directory is this repository, while the project is not checked in
+    let manifest = format!("{}\n[workspace]\n", basic_manifest("foo", "0.1.0"));
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/lib.rs", "pub fn a() -> i32 { let mut x = 1; x }\n")
+        .build();
+
+    let error = Fixer::new()
+        .manifest_path(p.root().join("Cargo.toml"))
+        .fix()
+        .unwrap_err();
+
+    assert!(
+        matches!(error.downcast_ref(), Some(FixitError::NoVcs)),
+        "unexpected error: {error:?}"
+    );
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let mut x = 1; x }\n"
+    );
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn leaves_reverted_files_out_of_report() {
+    let manifest = format!("{}\n[workspace]\n", basic_manifest("foo", "0.1.0"));
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file(
+            "src/lib.rs",
+            "mod other;\npub fn foo() -> i32 { let mut x = 1; x }\n",
+        )
+        .file(
+            "src/other.rs",
+            "pub fn bar() -> i32 { let mut y = 2; y }\n",
+        )
+        .build();
+    let lib = p.root().join("src/lib.rs");
+
+    let report = Fixer::new()
+        .manifest_path(p.root().join("Cargo.toml"))
+        .allow_no_vcs()
+        .verify(format!("grep -q 'let mut x' {}", lib.display()))
+        .bisect()
+        .fix()
+        .unwrap();
+
+    assert!(p.read_file("src/lib.rs").contains("let mut x"));
+    let files = report
+        .files
+        .iter()
+        .map(|file| (file.path.strip_prefix(p.root()).unwrap(), file.fixes))
+        .collect::<Vec<_>>();
+    assert_eq!(files, [(Path::new("src/other.rs"), 1)]);
+}
+
+#[cargo_test]
+fn rejects_conflicting_options() {
+    let error = Fixer::new().allow_no_vcs().bisect().fix().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "the following required arguments were not provided:\n  --verify <COMMAND>"
+    );
+
+    let error = Fixer::new()
+        .allow_no_vcs()
+        .speculative()
+        .broken_code()
+        .fix()
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "the argument '--speculative' cannot be used with '--broken-code'"
+    );
+
+    let error = Fixer::new()
+        .allow_no_vcs()
+        .verify("cargo 'test")
+        .fix()
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid value 'cargo 'test' for '--verify <COMMAND>': unterminated quote"
+    );
+}
@@ -1,9 +1,13 @@
 use cargo_test_support::basic_manifest;
 use cargo_test_support::cargo_test;
+use cargo_test_support::cross_compile;
+use cargo_test_support::git;
+use cargo_test_support::compare::assert_e2e;
 use cargo_test_support::compare::assert_ui;
 use cargo_test_support::project;
 use cargo_test_support::Project;
 use snapbox::str;
+use snapbox::IntoData;
 
 use crate::fix::FixitProject;
 
@@ -26,6 +30,7 @@ fn basic() {
         .with_stderr_data(str![[r#"
 [CHECKING] foo v0.0.1
 [FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -79,7 +84,7 @@ fn preserves_explicit_encoded_lint_caps() {
 
     p.cargo_("fixit --allow-no-vcs")
         .env("CARGO_ENCODED_RUSTFLAGS", "--cap-lints=deny")
-        .with_status(101)
+        .with_status(6)
         .with_stderr_contains("[ERROR] could not compile")
         .run();
 
@@ -161,6 +166,7 @@ fn fixable_and_unfixable() {
   = [HELP] maybe it is overwritten before being read?
   = [NOTE] `#[warn(unused_assignments)]` (part of `#[warn(unused)]`) on by default
 
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -231,6 +237,7 @@ fn print_errors_after_fixed() {
   |
   = [NOTE] `#[warn(dead_code)]` (part of `#[warn(unused)]`) on by default
 
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -252,7 +259,7 @@ fn metadata_error() {
         .build();
 
     p.cargo_("fixit --allow-no-vcs")
-        .with_status(101)
+        .with_status(5)
         .with_stderr_data(str![[r#"
 [ERROR] failed to run `cargo metadata`: `cargo metadata` exited with an [ERROR] [ERROR] unquoted keys cannot be empty, expected letters, numbers, `-`, `_`
  --> Cargo.toml:1:2
@@ -280,7 +287,7 @@ fn non_json_error() {
         .build();
 
     p.cargo_("fixit --allow-no-vcs --bin foo")
-        .with_status(101)
+        .with_status(8)
         .with_stderr_data(str![[r#"
 [ERROR] no bin target named `foo` in default-run packages
 [ERROR] could not compile
@@ -398,6 +405,7 @@ path = \"src/main.rs\"
      Checked foo v0.1.0 - app (bin)
      Checked foo v0.1.0 - foo (lib)
 [FIXED] src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -428,6 +436,7 @@ crate-type = ["rlib", "cdylib"]
 [CHECKING] foo v0.1.0
      Checked foo v0.1.0 - foo (lib)
 [FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -470,25 +479,128 @@ dep = {{ path = '../dep' }}
 
     p.cargo_("fixit --workspace --allow-no-vcs --target host-tuple --verbose")
         .with_stderr_data(str![[r#"
-     Checked app v0.1.0 - app (bin)
+     Checked app v0.1.0 - app (bin) for [HOST_TARGET]
      Checked app v0.1.0 - build-script-build (custom-build)
      Checked dep v0.1.0 - dep (lib)
-     Checked dep v0.1.0 - dep (lib)
+     Checked dep v0.1.0 - dep (lib) for [HOST_TARGET]
 [CHECKING] dep v0.1.0
-     Checked app v0.1.0 - app (bin)
+     Checked app v0.1.0 - app (bin) for [HOST_TARGET]
      Checked app v0.1.0 - build-script-build (custom-build)
      Checked dep v0.1.0 - dep (lib)
-     Checked dep v0.1.0 - dep (lib)
+     Checked dep v0.1.0 - dep (lib) for [HOST_TARGET]
 [FIXED] dep/src/lib.rs (1 fix)
 [CHECKING] app v0.1.0
-     Checked app v0.1.0 - app (bin)
+     Checked app v0.1.0 - app (bin) for [HOST_TARGET]
      Checked app v0.1.0 - build-script-build (custom-build)
 [FIXED] app/build.rs (1 fix)
-     Checked app v0.1.0 - app (bin)
+     Checked app v0.1.0 - app (bin) for [HOST_TARGET]
 [FIXED] app/src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn skips_uninstalled_target() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "pub fn foo() -> usize { let mut value = 1; value }\n")
+        .build();
+
+    // Tier 3 targets have no standard library to install.
+    p.cargo_("fixit --allow-no-vcs --target host-tuple --target x86_64-unknown-haiku")
+        .with_stderr_data(str![[r#"
+[WARNING] skipping `--target x86_64-unknown-haiku` as its standard library is not installed
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
+
+    assert_e2e().eq(
+        p.read_file("src/lib.rs"),
+        str![[r#"
+pub fn foo() -> usize { let value = 1; value }
+
+"#]],
+    );
+}
+
+#[cargo_test]
+fn suggests_installing_shipped_target() {
+    let target = cross_compile::unused();
+    // Its standard library is shipped by rustup, but rarely installed.
+    let installed = std::process::Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output()
+        .is_ok_and(|output| {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            stdout.lines().any(|line| line == target)
+        });
+    if installed {
+        return;
+    }
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "pub fn foo() -> usize { let mut value = 1; value }\n")
+        .build();
+
+    p.cargo_(&format!(
+        "fixit --allow-no-vcs --target host-tuple --target {target}"
+    ))
+    .with_stderr_data(str![[r#"
+[WARNING] skipping `--target wasm32-unknown-unknown` as its standard library is not installed
+[NOTE] run `rustup target add wasm32-unknown-unknown` to fix it as well
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+    .run();
+}
+
+#[cargo_test]
+fn fix_each_target_as_its_own_units() {
+    let alternate = cross_compile::alternate();
+    if !cross_compile::requires_target_installed(alternate) {
+        return;
+    }
+    // The variable only needs to be mutable on the alternate target, which fails to compile once
+    // the host's fix is applied.
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file(
+            "src/lib.rs",
+            &format!(
+                r#"pub fn foo() -> usize {{
+    let mut value = 1;
+    #[cfg(target_arch = "{}")]
+    {{
+        value += 1;
+    }}
+    value
+}}
+"#,
+                cross_compile::alternate_arch()
+            ),
+        )
+        .build();
+    let original = p.read_file("src/lib.rs");
+
+    p.cargo_(&format!(
+        "fixit --allow-no-vcs --target host-tuple --target {alternate}"
+    ))
+    .with_stderr_data(str![[r#"
+[CHECKING] foo v0.1.0
+[WARNING] dropped `unused_mut` fix at src/lib.rs:2:9 as it broke compilation: variable does not need to be mutable
+[WARNING] variable does not need to be mutable
+...
+"#]])
+    .run();
+
+    assert_eq!(p.read_file("src/lib.rs"), original);
 }
 
 #[cargo_test]
@@ -586,21 +698,105 @@ fn main(){ let mut a = 1; let _ = a; }
      Checked foo v0.1.0 - examp_b (example)
      Checked foo v0.1.0 - foo (lib)
      Checked foo v0.1.0 - foo (lib)
-[FIXED] src/lib.rs (2 fixes)
+[FIXED] src/lib.rs (1 fix)
      Checked foo v0.1.0 - app (bin)
      Checked foo v0.1.0 - app (bin)
      Checked foo v0.1.0 - test_a (test)
      Checked foo v0.1.0 - test_b (test)
      Checked foo v0.1.0 - examp_a (example)
      Checked foo v0.1.0 - examp_b (example)
-[FIXED] src/main.rs (2 fixes)
+     Checked foo v0.1.0 - foo (lib)
+     Checked foo v0.1.0 - foo (lib)
+[FIXED] src/main.rs (1 fix)
 [FIXED] tests/test_a.rs (2 fixes)
 [FIXED] tests/test_b.rs (2 fixes)
 [FIXED] examples/examp_a.rs (1 fix)
 [FIXED] examples/examp_b.rs (1 fix)
+[FIXED] src/lib.rs (1 fix)
+     Checked foo v0.1.0 - app (bin)
+     Checked foo v0.1.0 - app (bin)
+[FIXED] src/main.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn fix_order_unit_test_interleaved() {
+    // Checks the library while its unit tests are being checked, once, so that the diagnostics of
+    // the unit tests come before the library's artifact.
+    let wrapper = project()
+        .at("rustc-interleave")
+        .file("Cargo.toml", &basic_manifest("rustc-interleave", "0.0.1"))
+        .file(
+            "src/main.rs",
+            r#"
+            use std::path::{Path, PathBuf};
+            use std::process::Command;
+            use std::time::Duration;
+
+            fn wait_for(path: &Path) {
+                for _ in 0..500 {
+                    if path.exists() {
+                        break;
+                    }
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+            }
+
+            fn main() {
+                let mut args = std::env::args_os().skip(1);
+                let rustc = args.next().unwrap();
+                let args = args.collect::<Vec<_>>();
+                let dir = PathBuf::from(std::env::var_os("RUSTC_INTERLEAVE_DIR").unwrap());
+                let test_checked = dir.join("test-checked");
+                let lib_checked = dir.join("lib-checked");
+                let interleave = std::env::var_os("CARGO_PKG_NAME").is_some();
+                let is_test = args.iter().any(|arg| arg == "--test");
+                if interleave && !is_test && !lib_checked.exists() {
+                    wait_for(&test_checked);
+                }
+                let status = Command::new(rustc).args(&args).status().unwrap();
+                if interleave && is_test && !test_checked.exists() {
+                    std::fs::write(&test_checked, "").unwrap();
+                    wait_for(&lib_checked);
+                    std::thread::sleep(Duration::from_millis(500));
+                } else if interleave && !is_test && !lib_checked.exists() {
+                    std::fs::write(&lib_checked, "").unwrap();
+                }
+                std::process::exit(status.code().unwrap_or(1));
+            }
+            "#,
+        )
+        .build();
+    wrapper.cargo_("build").run();
+
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+pub fn foo() -> i32 { let mut a = 1; a }
+
+#[test]
+fn bar() { let mut b = 1; let _ = b; }
+"#,
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --all-targets --jobs 2")
+        .env("RUSTC_WORKSPACE_WRAPPER", wrapper.bin("rustc-interleave"))
+        .env("RUSTC_INTERLEAVE_DIR", p.root())
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
+    assert!(p.root().join("lib-checked").exists());
 }
 
 #[cargo_test]
@@ -638,6 +834,7 @@ resolver = "2"
      Checked b v0.1.0 - b (lib)
 [FIXED] a/src/lib.rs (1 fix)
 [FIXED] b/src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -721,6 +918,7 @@ fn fix_order_serial_packages() {
 [CHECKING] a v0.1.0
      Checked a v0.1.0 - a (lib)
 [FIXED] a/src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -758,6 +956,7 @@ resolver = "2"
      Checked a v0.1.0 - a (lib)
      Checked b v0.1.0 - b (lib)
 [FIXED] a/src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -860,6 +1059,7 @@ a = {{ path = '../a' }}
 [FIXED] b/src/lib.rs (1 fix)
      Checked a v0.1.0 - cycle (test)
 [FIXED] a/tests/cycle.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -900,16 +1100,17 @@ shared = {{ path = '../shared' }}
     p.cargo_("fixit --workspace --target host-tuple --allow-no-vcs --verbose")
         .with_stderr_data(str![[r#"
      Checked app v0.1.0 - build-script-build (custom-build)
-     Checked app v0.1.0 - app (lib)
-     Checked shared v0.1.0 - shared (lib)
+     Checked app v0.1.0 - app (lib) for [HOST_TARGET]
      Checked shared v0.1.0 - shared (lib)
+     Checked shared v0.1.0 - shared (lib) for [HOST_TARGET]
 [CHECKING] shared v0.1.0
      Checked app v0.1.0 - build-script-build (custom-build)
-     Checked app v0.1.0 - app (lib)
-     Checked shared v0.1.0 - shared (lib)
+     Checked app v0.1.0 - app (lib) for [HOST_TARGET]
      Checked shared v0.1.0 - shared (lib)
+     Checked shared v0.1.0 - shared (lib) for [HOST_TARGET]
 [FIXED] shared/src/lib.rs (1 fix)
 [CHECKING] app v0.1.0
+    Recorded run 1, undo it with `cargo fixit --undo 1`
 
 "#]])
         .run();
@@ -1034,3 +1235,2401 @@ codegen::generate!();
         .contains("use std::mem::replace;"));
     p.cargo_("check --workspace").run();
 }
+
+#[cargo_test]
+fn lint_allow_list() {
+    let p = lint_selection_project();
+
+    p.cargo_("fixit --allow-no-vcs --lint unused-mut")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[WARNING] unnecessary parentheses around assigned value
+ --> src/lib.rs:1:33
+  |
+1 | pub fn a() -> i32 { let value = (1); value }
+  |                                 ^ ^
+  |
+  = [NOTE] `#[warn(unused_parens)]` (part of `#[warn(unused)]`) on by default
+[HELP] remove these parentheses
+  |
+1 - pub fn a() -> i32 { let value = (1); value }
+1 + pub fn a() -> i32 { let value = 1 ; value }
+  |
+
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let value = (1); value }\n"
+    );
+}
+
+#[cargo_test]
+fn lint_deny_list() {
+    let p = lint_selection_project();
+
+    p.cargo_("fixit --allow-no-vcs --skip-lint unused_mut")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[WARNING] variable does not need to be mutable
+ --> src/lib.rs:1:25
+  |
+1 | pub fn a() -> i32 { let mut value = 1 ; value }
+  |                         ----^^^^^
+  |                         |
+  |                         [HELP] remove this `mut`
+  |
+  = [NOTE] `#[warn(unused_mut)]` (part of `#[warn(unused)]`) on by default
+
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let mut value = 1 ; value }\n"
+    );
+}
+
+#[cargo_test]
+fn lint_group_and_glob() {
+    let p = lint_selection_project();
+
+    p.cargo_("fixit --allow-no-vcs --lint unused --skip-lint unused_p*")
+        .run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let value = (1); value }\n"
+    );
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn lint_groups_from_rustc_env() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "pub fn a() -> i32 { let mut value = (1); value }\n",
+        )
+        .file(
+            "rustc.sh",
+            "#!/bin/sh\necho \"$@\" >> \"$(dirname \"$0\")/rustc.log\"\nexec rustc \"$@\"\n",
+        )
+        .build();
+    let rustc = p.root().join("rustc.sh");
+    std::fs::set_permissions(&rustc, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    p.cargo_("fixit --allow-no-vcs --lint unused")
+        .env("RUSTC", &rustc)
+        .run();
+
+    assert!(p.read_file("rustc.log").lines().any(|line| line == "-W help"));
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let value = 1 ; value }\n"
+    );
+}
+
+fn lint_selection_project() -> Project {
+    project()
+        .file(
+            "src/lib.rs",
+            "pub fn a() -> i32 { let mut value = (1); value }\n",
+        )
+        .build()
+}
+
+#[cargo_test]
+fn config_in_workspace_metadata() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                "{}
+[workspace.metadata.fixit]
+skip-lints = ['unused_mut']
+",
+                basic_manifest("foo", "0.0.1")
+            ),
+        )
+        .file(
+            "src/lib.rs",
+            "pub fn a() -> i32 { let mut value = (1); value }\n",
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs").run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let mut value = 1 ; value }\n"
+    );
+
+    // The command line takes precedence.
+    p.cargo_("fixit --allow-no-vcs --skip-lint unused_parens").run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let value = 1 ; value }\n"
+    );
+}
+
+#[cargo_test]
+fn config_clippy_overridden_by_no_clippy() {
+    let p = project()
+        .file("fixit.toml", "clippy = true\n")
+        .file("src/lib.rs", "pub fn a() -> i32 { return 1; }\n")
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --no-clippy").run();
+
+    assert_eq!(p.read_file("src/lib.rs"), "pub fn a() -> i32 { return 1; }\n");
+
+    p.cargo_("fixit --allow-no-vcs").run();
+
+    assert_eq!(p.read_file("src/lib.rs"), "pub fn a() -> i32 { 1}\n");
+}
+
+#[cargo_test]
+fn config_per_directory() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"[workspace]
+members = ["a", "b", "c"]
+resolver = "2"
+"#,
+        )
+        .file(
+            "fixit.toml",
+            r#"skip-lints = ["unused_mut"]
+exclude-packages = ["c"]
+exclude = ["b/src/generated.rs"]
+"#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/fixit.toml", "skip-lints = []\n")
+        .file(
+            "a/src/lib.rs",
+            "pub fn a() -> i32 { let mut value = (1); value }\n",
+        )
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file(
+            "b/src/lib.rs",
+            "mod generated;\npub fn b() -> i32 { let mut value = (1); value }\n",
+        )
+        .file(
+            "b/src/generated.rs",
+            "pub fn g() -> i32 { let value = (1); value }\n",
+        )
+        .file("c/Cargo.toml", &basic_manifest("c", "0.1.0"))
+        .file(
+            "c/src/lib.rs",
+            "pub fn c() -> i32 { let mut value = (1); value }\n",
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --workspace").run();
+
+    assert_eq!(
+        p.read_file("a/src/lib.rs"),
+        "pub fn a() -> i32 { let value = 1 ; value }\n"
+    );
+    assert_eq!(
+        p.read_file("b/src/lib.rs"),
+        "mod generated;\npub fn b() -> i32 { let mut value = 1 ; value }\n"
+    );
+    assert_eq!(
+        p.read_file("b/src/generated.rs"),
+        "pub fn g() -> i32 { let value = (1); value }\n"
+    );
+    assert_eq!(
+        p.read_file("c/src/lib.rs"),
+        "pub fn c() -> i32 { let mut value = (1); value }\n"
+    );
+
+    // Packages are only left out when not selected by name.
+    p.cargo_("fixit --allow-no-vcs -p c").run();
+
+    assert_eq!(
+        p.read_file("c/src/lib.rs"),
+        "pub fn c() -> i32 { let mut value = 1 ; value }\n"
+    );
+}
+
+#[cargo_test]
+fn config_workspace_setting_in_directory() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"[workspace]
+members = ["crates/a"]
+resolver = "2"
+"#,
+        )
+        .file("crates/a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("crates/a/src/lib.rs", "")
+        .file("crates/fixit.toml", "clippy = true\n")
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs")
+        .with_stderr_data(str![[r#"
+[ERROR] `clippy` in `[ROOT]/foo/crates/fixit.toml` can only be set for the whole workspace
+
+"#]])
+        .with_status(101)
+        .run();
+}
+
+#[cargo_test]
+fn config_in_subdirectory_of_package() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "mod generated;\npub fn a() -> i32 { let mut value = 1; value }\n",
+        )
+        .file("src/generated/fixit.toml", "skip-lints = [\"unused_mut\"]\n")
+        .file(
+            "src/generated/mod.rs",
+            "pub fn g() -> i32 { let mut value = 1; value }\n",
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs").run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "mod generated;\npub fn a() -> i32 { let value = 1; value }\n"
+    );
+    assert_eq!(
+        p.read_file("src/generated/mod.rs"),
+        "pub fn g() -> i32 { let mut value = 1; value }\n"
+    );
+}
+
+#[cargo_test]
+fn config_unreadable() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("fixit.toml/README", "")
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs")
+        .with_stderr_data(str![[r#"
+[ERROR] failed to read `[ROOT]/foo/fixit.toml`: Is a directory (os error 21)
+
+"#]])
+        .with_status(101)
+        .run();
+}
+
+#[cargo_test]
+fn dry_run() {
+    let original = "pub fn a() {
+    let mut b = 10;
+    let _ = b;
+}
+";
+    let p = project().file("src/lib.rs", original).build();
+
+    p.cargo_("fixit --dry-run")
+        .with_stdout_data(str![[r#"
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,4 +1,4 @@
+ pub fn a() {
+-    let mut b = 10;
++    let b = 10;
+     let _ = b;
+ }
+
+"#]])
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+
+"#]])
+        .run();
+
+    assert_eq!(p.read_file("src/lib.rs"), original);
+}
+
+#[cargo_test]
+fn dry_run_leaves_workspace_build_alone() {
+    let p = project()
+        .file("src/lib.rs", "pub fn foo() -> i32 { let mut x = 1; x }\n")
+        .build();
+
+    p.cargo_("fixit --dry-run").run();
+    p.cargo_("fixit --allow-no-vcs")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r#"
+pub fn foo() -> i32 { let x = 1; x }
+
+"#]]);
+}
+
+/// A project that only compiles with a `--cfg` set by a config file above the workspace.
+fn ancestor_config_project() -> Project {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"#[cfg(not(needed))]
+compile_error!("missing cfg");
+
+pub fn foo() -> i32 { let mut x = 1; x }
+"#,
+        )
+        .build();
+    let config = p.root().parent().unwrap().join(".cargo");
+    std::fs::create_dir_all(&config).unwrap();
+    std::fs::write(
+        config.join("config.toml"),
+        "[build]\nrustflags = [\"--cfg\", \"needed\", \"--check-cfg\", \"cfg(needed)\"]\n",
+    )
+    .unwrap();
+    p
+}
+
+#[cargo_test]
+fn dry_run_ancestor_config() {
+    let p = ancestor_config_project();
+
+    p.cargo_("fixit --dry-run")
+        .with_stdout_data(str![[r#"
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,4 +1,4 @@
+ #[cfg(not(needed))]
+ compile_error!("missing cfg");
+ 
+-pub fn foo() -> i32 { let mut x = 1; x }
++pub fn foo() -> i32 { let x = 1; x }
+
+"#]])
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn dry_run_ancestor_config_with_target_dir_elsewhere() {
+    let p = project()
+        .at("outer/foo")
+        .file(
+            "src/lib.rs",
+            r#"#[cfg(not(needed))]
+compile_error!("missing cfg");
+
+pub fn foo() -> i32 { let mut x = 1; x }
+"#,
+        )
+        .build();
+    let outer = p.root().parent().unwrap().to_owned();
+    std::fs::create_dir_all(outer.join(".cargo")).unwrap();
+    std::fs::write(
+        outer.join(".cargo/config.toml"),
+        "[build]\nrustflags = [\"--cfg\", \"needed\", \"--check-cfg\", \"cfg(needed)\"]\n",
+    )
+    .unwrap();
+
+    p.cargo_("fixit --dry-run")
+        .env("CARGO_TARGET_DIR", outer.parent().unwrap().join("elsewhere"))
+        .with_stdout_data(str![[r#"
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,4 +1,4 @@
+ #[cfg(not(needed))]
+ compile_error!("missing cfg");
+ 
+-pub fn foo() -> i32 { let mut x = 1; x }
++pub fn foo() -> i32 { let x = 1; x }
+
+"#]])
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn dry_run_path_dependency_outside_workspace() {
+    project()
+        .at("dep")
+        .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+        .file("src/lib.rs", "pub fn dep() -> i32 { 1 }\n")
+        .build();
+    let original = "pub fn foo() -> i32 { let mut x = dep::dep(); x }\n";
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                "{}
+[dependencies]
+dep = {{ path = '../dep' }}
+",
+                basic_manifest("foo", "0.0.1")
+            ),
+        )
+        .file("src/lib.rs", original)
+        .build();
+
+    p.cargo_("fixit --dry-run")
+        .with_stdout_data(str![[r#"
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1 @@
+-pub fn foo() -> i32 { let mut x = dep::dep(); x }
++pub fn foo() -> i32 { let x = dep::dep(); x }
+
+"#]])
+        .with_stderr_data(str![[r#"
+[CHECKING] dep v0.1.0
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), original);
+
+    // The dependency is still built from the last run.
+    p.cargo_("fixit --dry-run")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn dry_run_workspace() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"[workspace]
+members = ["a", "b"]
+resolver = "2"
+"#,
+        )
+        .file(
+            "a/Cargo.toml",
+            &format!(
+                "{}
+[dependencies]
+b = {{ path = '../b' }}
+",
+                basic_manifest("a", "0.1.0")
+            ),
+        )
+        .file(
+            "a/src/lib.rs",
+            "pub fn a() -> usize { let mut value = b::b(); value }\n",
+        )
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file(
+            "b/src/lib.rs",
+            "pub fn b() -> usize { let mut value = 1; value }\n",
+        )
+        .build();
+
+    p.cargo_("fixit --workspace --dry-run")
+        .with_stdout_data(str![[r#"
+--- a/b/src/lib.rs
++++ b/b/src/lib.rs
+@@ -1 +1 @@
+-pub fn b() -> usize { let mut value = 1; value }
++pub fn b() -> usize { let value = 1; value }
+--- a/a/src/lib.rs
++++ b/a/src/lib.rs
+@@ -1 +1 @@
+-pub fn a() -> usize { let mut value = b::b(); value }
++pub fn a() -> usize { let value = b::b(); value }
+
+"#]])
+        .run();
+
+    assert!(p.read_file("a/src/lib.rs").contains("let mut value"));
+    assert!(p.read_file("b/src/lib.rs").contains("let mut value"));
+}
+
+#[cargo_test]
+fn message_format_json() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+            pub fn a() {
+                let mut b = 10;
+                let _ = b;
+
+                let mut c = 10;
+                let _ = c;
+                c = 1;
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --message-format json")
+        .with_stdout_data(
+            str![[r#"
+[
+  {
+    "package_id": "path+[ROOTURL]/foo#0.0.1",
+    "reason": "unit-scheduled",
+    "target_kind": "lib"
+  },
+  {
+    "applicability": "machine",
+    "code": "unused_mut",
+    "file": "src/lib.rs",
+    "message": "variable does not need to be mutable",
+    "package_id": "path+[ROOTURL]/foo#0.0.1",
+    "reason": "suggestion-applied",
+    "replacements": [
+      {
+        "byte_end": 50,
+        "byte_start": 46,
+        "replacement": ""
+      }
+    ],
+    "target_kind": "lib"
+  },
+  {
+    "files": [
+      {
+        "file": "src/lib.rs",
+        "fixes": 1
+      }
+    ],
+    "package_id": "path+[ROOTURL]/foo#0.0.1",
+    "reason": "unit-finished",
+    "target_kind": "lib"
+  },
+  {
+    "code": "unused_assignments",
+    "message": "value assigned to `c` is never read",
+    "package_id": "path+[ROOTURL]/foo#0.0.1",
+    "reason": "diagnostic-remaining",
+    "rendered": "[WARNING] value assigned to `c` is never read\n --> src/lib.rs:8:17\n  |\n8 |                 c = 1;\n  |                 ^^^^^\n  |\n  = [HELP] maybe it is overwritten before being read?\n  = [NOTE] `#[warn(unused_assignments)]` (part of `#[warn(unused)]`) on by default\n\n",
+    "target_kind": "lib"
+  }
+]
+"#]]
+            .is_json()
+            .against_jsonlines(),
+        )
+        .run();
+}
+
+#[cargo_test]
+fn dry_run_message_format_json() {
+    let p = project()
+        .file("src/lib.rs", "pub fn foo() -> i32 { let mut x = 1; x }\n")
+        .build();
+
+    p.cargo_("fixit --dry-run --message-format json")
+        .with_stdout_data(
+            str![[r#"
+[
+  {
+    "package_id": "path+[ROOTURL]/foo#0.0.1",
+    "reason": "unit-scheduled",
+    "target_kind": "lib"
+  },
+  {
+    "applicability": "machine",
+    "code": "unused_mut",
+    "file": "src/lib.rs",
+    "message": "variable does not need to be mutable",
+    "package_id": "path+[ROOTURL]/foo#0.0.1",
+    "reason": "suggestion-applied",
+    "replacements": [
+      {
+        "byte_end": 30,
+        "byte_start": 26,
+        "replacement": ""
+      }
+    ],
+    "target_kind": "lib"
+  },
+  {
+    "files": [
+      {
+        "file": "src/lib.rs",
+        "fixes": 1
+      }
+    ],
+    "package_id": "path+[ROOTURL]/foo#0.0.1",
+    "reason": "unit-finished",
+    "target_kind": "lib"
+  }
+]
+"#]]
+            .is_json()
+            .against_jsonlines(),
+        )
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1 @@
+-pub fn foo() -> i32 { let mut x = 1; x }
++pub fn foo() -> i32 { let x = 1; x }
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn applicability_maybe_incorrect() {
+    let p = applicability_project();
+
+    p.cargo_("fixit --allow-no-vcs --applicability maybe-incorrect")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (2 fixes)
+[WARNING] `src/lib.rs` has fixes that are not machine-applicable and should be reviewed
+[NOTE] applied suggestions with `maybe-incorrect` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "fn f() -> Result<(), ()> { Ok(()) }
+pub fn a() { let _ = f(); let b = 1; let _ = b; }
+"
+    );
+}
+
+#[cargo_test]
+fn applicability_note_once_per_run() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!("{}\n[features]\na = []\n", basic_manifest("foo", "0.1.0")),
+        )
+        .file(
+            "src/lib.rs",
+            r#"fn f() -> Result<(), ()> { Ok(()) }
+pub fn a() { f(); }
+#[cfg(feature = "a")]
+pub fn b() { f(); }
+"#,
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --applicability maybe-incorrect --each-feature")
+        .with_stderr_data(str![[r#"
+[FIXING] foo with no features
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+[WARNING] `src/lib.rs` has fixes that are not machine-applicable and should be reviewed
+[FIXING] foo with feature `a`
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+[WARNING] `src/lib.rs` has fixes that are not machine-applicable and should be reviewed
+[SUMMARY] foo with no features: 1 fix, 0 warnings remaining
+[SUMMARY] foo with feature `a`: 1 fix, 0 warnings remaining
+[NOTE] applied suggestions with `maybe-incorrect` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn applicability_machine() {
+    let p = applicability_project();
+
+    p.cargo_("fixit --allow-no-vcs --applicability machine")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[WARNING] unused `Result` that must be used
+ --> src/lib.rs:2:14
+  |
+2 | pub fn a() { f(); let b = 1; let _ = b; }
+  |              ^^^
+  |
+  = [NOTE] this `Result` may be an `Err` variant, which should be handled
+  = [NOTE] `#[warn(unused_must_use)]` (part of `#[warn(unused)]`) on by default
+[HELP] use `let _ = ...` to ignore the resulting value
+  |
+2 | pub fn a() { let _ = f(); let b = 1; let _ = b; }
+  |              +++++++
+
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "fn f() -> Result<(), ()> { Ok(()) }
+pub fn a() { f(); let b = 1; let _ = b; }
+"
+    );
+}
+
+fn applicability_project() -> Project {
+    project()
+        .file(
+            "src/lib.rs",
+            "fn f() -> Result<(), ()> { Ok(()) }
+pub fn a() { f(); let mut b = 1; let _ = b; }
+",
+        )
+        .build()
+}
+
+#[cargo_test]
+fn interactive_accept_and_skip() {
+    let p = lint_selection_project();
+
+    p.cargo_("fixit --allow-no-vcs --interactive")
+        .with_stdin("y\nn\n")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[WARNING] unnecessary parentheses around assigned value
+ --> src/lib.rs:1:37
+  |
+1 | pub fn a() -> i32 { let mut value = (1); value }
+  |                                     ^ ^
+  |
+  = [NOTE] `#[warn(unused_parens)]` (part of `#[warn(unused)]`) on by default
+[HELP] remove these parentheses
+  |
+1 - pub fn a() -> i32 { let mut value = (1); value }
+1 + pub fn a() -> i32 { let mut value = 1 ; value }
+  |
+
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1 @@
+-pub fn a() -> i32 { let mut value = (1); value }
++pub fn a() -> i32 { let mut value = 1 ; value }
+? Apply this fix? [y]es, [n]o, [a]ll `unused_parens`, [q]uit [WARNING] variable does not need to be mutable
+ --> src/lib.rs:1:25
+  |
+1 | pub fn a() -> i32 { let mut value = (1); value }
+  |                         ----^^^^^
+  |                         |
+  |                         [HELP] remove this `mut`
+  |
+  = [NOTE] `#[warn(unused_mut)]` (part of `#[warn(unused)]`) on by default
+
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1 @@
+-pub fn a() -> i32 { let mut value = (1); value }
++pub fn a() -> i32 { let value = (1); value }
+? Apply this fix? [y]es, [n]o, [a]ll `unused_mut`, [q]uit [FIXED] src/lib.rs (1 fix)
+[WARNING] variable does not need to be mutable
+ --> src/lib.rs:1:25
+  |
+1 | pub fn a() -> i32 { let mut value = 1 ; value }
+  |                         ----^^^^^
+  |                         |
+  |                         [HELP] remove this `mut`
+  |
+  = [NOTE] `#[warn(unused_mut)]` (part of `#[warn(unused)]`) on by default
+
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let mut value = 1 ; value }\n"
+    );
+}
+
+#[cargo_test]
+fn interactive_accept_lint() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "pub fn a() -> i32 { let mut b = 1; let mut c = 2; b + c }\n",
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --interactive")
+        .with_stdin("a\n")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[WARNING] variable does not need to be mutable
+ --> src/lib.rs:1:25
+  |
+1 | pub fn a() -> i32 { let mut b = 1; let mut c = 2; b + c }
+  |                         ----^
+  |                         |
+  |                         [HELP] remove this `mut`
+  |
+  = [NOTE] `#[warn(unused_mut)]` (part of `#[warn(unused)]`) on by default
+
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1 @@
+-pub fn a() -> i32 { let mut b = 1; let mut c = 2; b + c }
++pub fn a() -> i32 { let b = 1; let mut c = 2; b + c }
+? Apply this fix? [y]es, [n]o, [a]ll `unused_mut`, [q]uit [FIXED] src/lib.rs (2 fixes)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let b = 1; let c = 2; b + c }\n"
+    );
+}
+
+#[cargo_test]
+fn interactive_quit() {
+    let p = lint_selection_project();
+
+    p.cargo_("fixit --allow-no-vcs --interactive")
+        .with_stdin("q\n")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[WARNING] unnecessary parentheses around assigned value
+ --> src/lib.rs:1:37
+  |
+1 | pub fn a() -> i32 { let mut value = (1); value }
+  |                                     ^ ^
+  |
+  = [NOTE] `#[warn(unused_parens)]` (part of `#[warn(unused)]`) on by default
+[HELP] remove these parentheses
+  |
+1 - pub fn a() -> i32 { let mut value = (1); value }
+1 + pub fn a() -> i32 { let mut value = 1 ; value }
+  |
+
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1 @@
+-pub fn a() -> i32 { let mut value = (1); value }
++pub fn a() -> i32 { let mut value = 1 ; value }
+? Apply this fix? [y]es, [n]o, [a]ll `unused_parens`, [q]uit [WARNING] unnecessary parentheses around assigned value
+ --> src/lib.rs:1:37
+  |
+1 | pub fn a() -> i32 { let mut value = (1); value }
+  |                                     ^ ^
+  |
+  = [NOTE] `#[warn(unused_parens)]` (part of `#[warn(unused)]`) on by default
+[HELP] remove these parentheses
+  |
+1 - pub fn a() -> i32 { let mut value = (1); value }
+1 + pub fn a() -> i32 { let mut value = 1 ; value }
+  |
+
+[WARNING] variable does not need to be mutable
+ --> src/lib.rs:1:25
+  |
+1 | pub fn a() -> i32 { let mut value = (1); value }
+  |                         ----^^^^^
+  |                         |
+  |                         [HELP] remove this `mut`
+  |
+  = [NOTE] `#[warn(unused_mut)]` (part of `#[warn(unused)]`) on by default
+
+
+"#]])
+        .run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() -> i32 { let mut value = (1); value }\n"
+    );
+}
+
+#[cargo_test]
+fn multi_file_suggestion() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "pub fn f(x: &Vec<i32>) -> Vec<i32> { include!(\"body.rs\") }\n",
+        )
+        .file("src/body.rs", "x.clone()\n")
+        .build();
+
+    p.cargo_("fixit --clippy --allow-no-vcs --applicability unspecified")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[FIXED] src/body.rs (1 fix)
+[WARNING] `src/lib.rs` has fixes that are not machine-applicable and should be reviewed
+[WARNING] `src/body.rs` has fixes that are not machine-applicable and should be reviewed
+[NOTE] applied suggestions with `unspecified` applicability or better
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn f(x: &[i32]) -> Vec<i32> { include!(\"body.rs\") }\n"
+    );
+    assert_eq!(p.read_file("src/body.rs"), "x.to_owned()\n");
+}
+
+#[cargo_test]
+fn journal_removed_after_run() {
+    let p = project()
+        .file("src/lib.rs", "pub fn a() { let mut b = 10; let _ = b; }")
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs").run();
+
+    assert!(p.read_file("src/lib.rs").contains("let b = 10;"));
+    assert!(!p.root().join("target/fixit/journal").exists());
+}
+
+#[cargo_test]
+fn interrupted_run_blocks_new_run() {
+    let p = interrupted_project();
+
+    p.cargo_("fixit --allow-no-vcs")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[ERROR] a previous `cargo fixit` run was interrupted before it finished
+run `cargo fixit --recover` to restore the files it modified, or `cargo fixit --recover=finalize` to keep its fixes
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), "pub fn a() {}\n");
+}
+
+#[cargo_test]
+fn recover_restores_interrupted_run() {
+    let p = interrupted_project();
+
+    p.cargo_("fixit --recover")
+        .with_stderr_data(str![[r#"
+[NOTE] reverting `src/lib.rs` to its original state
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), "pub fn a( ) {}\n");
+    assert_eq!(p.read_file("src/main.rs"), "fn main() {}\n");
+    assert!(!p.root().join("target/fixit/journal").exists());
+
+    p.cargo_("fixit --recover")
+        .with_stderr_data(str![[r#"
+[NOTE] no interrupted `cargo fixit` run to recover
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn recover_finalize_keeps_fixes() {
+    let p = interrupted_project();
+
+    p.cargo_("fixit --recover=finalize")
+        .with_stderr_data(str![[r#"
+[NOTE] keeping the fixes in `src/lib.rs`
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), "pub fn a() {}\n");
+    assert!(!p.root().join("target/fixit/journal").exists());
+
+    p.cargo_("fixit --allow-no-vcs").run();
+}
+
+/// A project left behind by a run that was killed after fixing `src/lib.rs`.
+fn interrupted_project() -> Project {
+    let p = project()
+        .file("src/lib.rs", "pub fn a() {}\n")
+        .file("src/main.rs", "fn main() {}\n")
+        .file("target/fixit/journal/0", "pub fn a( ) {}\n")
+        .file("target/fixit/journal/1", "fn main() {}\n")
+        .build();
+    let index = [("src/lib.rs", "0"), ("src/main.rs", "1")]
+        .into_iter()
+        .map(|(path, original)| {
+            let path = p.root().join(path);
+            format!(
+                "{}\n",
+                serde_json::json!({ "path": path, "original": original })
+            )
+        })
+        .collect::<String>();
+    std::fs::write(p.root().join("target/fixit/journal/index"), index).unwrap();
+    p
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn interrupt_restores_files() {
+    let p = project()
+        .file("src/lib.rs", "pub fn a() { let mut b = 10; let _ = b; }")
+        .build();
+
+    let stderr = interrupt_fixit(&p, "fixit --allow-no-vcs", false);
+
+    assert_e2e().eq(
+        stderr,
+        str![[r#"
+[CHECKING] foo v0.0.1
+[NOTE] reverting `src/lib.rs` to its original state
+[ERROR] interrupted
+
+"#]],
+    );
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn a() { let mut b = 10; let _ = b; }"
+    );
+    assert!(!p.root().join("target/fixit/journal").exists());
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn interrupt_keeps_fixes() {
+    let p = project()
+        .file("src/lib.rs", "pub fn a() { let mut b = 10; let _ = b; }")
+        .build();
+
+    let stderr = interrupt_fixit(&p, "fixit --allow-no-vcs --on-interrupt=finalize", false);
+
+    assert_e2e().eq(
+        stderr,
+        str![[r#"
+[CHECKING] foo v0.0.1
+[NOTE] keeping the fixes in `src/lib.rs`
+[ERROR] interrupted
+
+"#]],
+    );
+    assert!(p.read_file("src/lib.rs").contains("let b = 10;"));
+    assert!(!p.root().join("target/fixit/journal").exists());
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn interrupt_process_group() {
+    let p = project()
+        .file("src/lib.rs", "pub fn a() { let mut b = 10; let _ = b; }")
+        .build();
+
+    // Like Ctrl-C in a terminal, which also stops Cargo, possibly before fixit notices.
+    let stderr = interrupt_fixit(&p, "fixit --allow-no-vcs --on-interrupt=finalize", true);
+
+    assert_e2e().eq(
+        stderr,
+        str![[r#"
+[CHECKING] foo v0.0.1
+[NOTE] keeping the fixes in `src/lib.rs`
+[ERROR] interrupted
+
+"#]],
+    );
+    assert!(p.read_file("src/lib.rs").contains("let b = 10;"));
+    assert!(!p.root().join("target/fixit/journal").exists());
+}
+
+/// Runs fixit, sending it `SIGTERM` while it checks the fixes it applied, or `SIGINT` to its
+/// whole process group.
+///
+/// Returns what fixit printed to stderr.
+#[cfg(unix)]
+fn interrupt_fixit(p: &Project, args: &str, process_group: bool) -> String {
+    use std::os::unix::process::CommandExt;
+    use std::process::Stdio;
+
+    // Stalls the check after the first one, once, reporting fixit's pid for the signal.
+    let wrapper = project()
+        .at("rustc-stall")
+        .file("Cargo.toml", &basic_manifest("rustc-stall", "0.0.1"))
+        .file(
+            "src/main.rs",
+            r#"
+            use std::os::unix::process::parent_id;
+            use std::path::Path;
+            use std::process::Command;
+
+            fn main() {
+                let mut args = std::env::args_os().skip(1);
+                let rustc = args.next().unwrap();
+                let dir = std::env::var_os("RUSTC_STALL_DIR").unwrap();
+                let checked = Path::new(&dir).join("checked");
+                let stalled = Path::new(&dir).join("stalled");
+                if std::env::var_os("CARGO_PKG_NAME").is_some()
+                    && checked.exists()
+                    && !stalled.exists()
+                {
+                    let cargo = parent_id();
+                    let fixit = Command::new("ps")
+                        .args(["-o", "ppid=", "-p", &cargo.to_string()])
+                        .output()
+                        .unwrap()
+                        .stdout;
+                    std::fs::write(&stalled, fixit).unwrap();
+                    while parent_id() == cargo {
+                        std::thread::sleep(std::time::Duration::from_millis(20));
+                    }
+                    std::process::exit(1);
+                }
+                if std::env::var_os("CARGO_PKG_NAME").is_some() {
+                    std::fs::write(&checked, "").unwrap();
+                }
+                let status = Command::new(rustc).args(args).status().unwrap();
+                std::process::exit(status.code().unwrap_or(1));
+            }
+            "#,
+        )
+        .build();
+    wrapper.cargo_("build").run();
+
+    let child = p
+        .cargo_(args)
+        .env("RUSTC_WORKSPACE_WRAPPER", wrapper.bin("rustc-stall"))
+        .env("RUSTC_STALL_DIR", p.root())
+        .build_command()
+        .process_group(0)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let stalled = p.root().join("stalled");
+    let start = std::time::Instant::now();
+    let fixit = loop {
+        if let Ok(pid) = std::fs::read_to_string(&stalled) {
+            if !pid.trim().is_empty() {
+                break pid.trim().to_owned();
+            }
+        }
+        assert!(
+            start.elapsed() < std::time::Duration::from_secs(60),
+            "fixit never checked its fixes"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    };
+    let mut kill = std::process::Command::new("kill");
+    if process_group {
+        kill.args(["-INT", "--", &format!("-{}", child.id())]);
+    } else {
+        kill.args(["-TERM", &fixit]);
+    }
+    let status = kill.status().unwrap();
+    assert!(status.success());
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130));
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[cargo_test]
+fn undo_last_run() {
+    let p = undo_project();
+
+    p.cargo_("fixit --allow-no-vcs").run();
+    assert!(p.read_file("src/lib.rs").contains("let b = 10;"));
+
+    p.cargo_("fixit --undo")
+        .with_stderr_data(str![[r#"
+[NOTE] undoing the fixes in `src/lib.rs`
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), UNDO_SOURCE);
+
+    p.cargo_("fixit --undo")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[ERROR] no `cargo fixit` run to undo
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn undo_keeps_other_edits() {
+    let p = undo_project();
+
+    let output = p.cargo_("fixit --allow-no-vcs").run();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let run_id = stderr
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Recorded run "))
+        .and_then(|line| line.split(',').next())
+        .unwrap();
+    let edited = p
+        .read_file("src/lib.rs")
+        .replace("pub fn c() {}", "pub fn c() { todo!() }");
+    p.change_file("src/lib.rs", &edited);
+
+    p.cargo_(&format!("fixit --undo {run_id}"))
+        .with_stderr_data(str![[r#"
+[NOTE] undoing the fixes in `src/lib.rs`
+
+"#]])
+        .run();
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        UNDO_SOURCE.replace("pub fn c() {}", "pub fn c() { todo!() }")
+    );
+}
+
+#[cargo_test]
+fn undo_refuses_diverged_file() {
+    let p = undo_project();
+
+    p.cargo_("fixit --allow-no-vcs").run();
+    let edited = p.read_file("src/lib.rs").replace("let b = 10;", "let b = 11;");
+    p.change_file("src/lib.rs", &edited);
+
+    p.cargo_("fixit --undo")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[ERROR] unable to undo run `[..]` as these files were changed where it made fixes:
+
+  * src/lib.rs
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), edited);
+
+    p.cargo_("fixit --undo 2")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[ERROR] no run `2` to undo; runs that can be undone are `1`
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn undo_keeps_last_runs() {
+    let p = undo_project();
+    let runs_dir = p.root().join("target/fixit/runs");
+    for id in 1..=10 {
+        let run_dir = runs_dir.join(id.to_string());
+        std::fs::create_dir_all(&run_dir).unwrap();
+        std::fs::write(run_dir.join("run.json"), "[]").unwrap();
+    }
+
+    p.cargo_("fixit --allow-no-vcs").run();
+
+    let mut ids = std::fs::read_dir(&runs_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    ids.sort();
+    assert_eq!(ids.len(), 10, "runs {ids:?}");
+    assert!(!ids.contains(&"1".to_owned()), "oldest run is removed");
+    assert!(ids.contains(&"2".to_owned()));
+    assert!(ids.contains(&"11".to_owned()), "new run follows the latest");
+}
+
+const UNDO_SOURCE: &str = "\
+pub fn a() {
+    let mut b = 10;
+    let _ = b;
+}
+
+pub fn c() {}
+";
+
+fn undo_project() -> Project {
+    project().file("src/lib.rs", UNDO_SOURCE).build()
+}
+
+#[cargo_test]
+fn commit_single() {
+    let (p, repo) = commit_project();
+
+    p.cargo_("fixit --commit single")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (2 fixes)
+   Committed Fix warnings (2 fixes in 1 file)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_eq!(commit_messages(&repo), ["Fix warnings (2 fixes in 1 file)"]);
+    assert_clean(&repo);
+}
+
+#[cargo_test]
+fn commit_lint() {
+    let (p, repo) = commit_project();
+
+    p.cargo_("fixit --commit lint")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+   Committed Fix unused_mut (1 fix in 1 file)
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+   Committed Fix unused_parens (1 fix in 1 file)
+[CHECKING] foo v0.0.1
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_eq!(
+        commit_messages(&repo),
+        [
+            "Fix unused_parens (1 fix in 1 file)",
+            "Fix unused_mut (1 fix in 1 file)",
+        ]
+    );
+    assert_clean(&repo);
+}
+
+#[cargo_test]
+fn commit_package_on_branch() {
+    let (p, repo) = git::new_repo("ws", |p| {
+        p.file(".gitignore", "/target\n/Cargo.lock\n")
+            .file(
+                "Cargo.toml",
+                r#"
+                [workspace]
+                members = ["a", "b"]
+                resolver = "3"
+                "#,
+            )
+            .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+            .file("a/src/lib.rs", "pub fn a() { let mut x = 1; let _ = x; }")
+            .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+            .file("b/src/lib.rs", "pub fn b() { let mut x = 1; let _ = x; }")
+    });
+
+    p.cargo_("fixit --commit package --branch lint-fixes").run();
+
+    assert_eq!(
+        repo.head().unwrap().shorthand(),
+        Some("lint-fixes"),
+        "fixes are committed to the new branch"
+    );
+    assert_eq!(
+        commit_messages(&repo),
+        [
+            "Fix warnings in b (1 fix in 1 file)",
+            "Fix warnings in a (1 fix in 1 file)",
+        ]
+    );
+    assert_clean(&repo);
+}
+
+#[cargo_test]
+fn commit_refuses_allow_dirty() {
+    let (p, repo) = commit_project();
+    p.change_file("src/lib.rs", "pub fn a() -> i32 { let mut b = (10); b }\n// edit\n");
+
+    p.cargo_("fixit --commit single --allow-dirty")
+        .with_status(2)
+        .with_stderr_data(str![[r#"
+[ERROR] the argument '--commit <GROUPING>' cannot be used with '--allow-dirty'
+
+Usage: cargo fixit --commit <GROUPING>
+
+For more information, try '--help'.
+
+"#]])
+        .run();
+    assert!(commit_messages(&repo).is_empty());
+    assert!(p.read_file("src/lib.rs").contains("let mut b"));
+}
+
+#[cargo_test]
+fn commit_failure_keeps_fixes_for_undo() {
+    let (p, repo) = commit_project();
+    let mut config = repo.config().unwrap();
+    config.remove("user.name").unwrap();
+    config.remove("user.email").unwrap();
+
+    p.cargo_("fixit --commit lint")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[NOTE] kept the fixes made before the error
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+[ERROR] failed to determine the author of the commit: config value 'user.name' was not found; class=Config (7); code=NotFound (-3)
+
+"#]])
+        .run();
+    assert!(commit_messages(&repo).is_empty());
+    assert!(p.read_file("src/lib.rs").contains("let b"));
+
+    p.cargo_("fixit --undo")
+        .with_stderr_data(str![[r#"
+[NOTE] undoing the fixes in `src/lib.rs`
+
+"#]])
+        .run();
+    assert_clean(&repo);
+}
+
+fn commit_project() -> (Project, git2::Repository) {
+    git::new_repo("foo", |p| {
+        p.file(".gitignore", "/target\n/Cargo.lock\n").file(
+            "src/lib.rs",
+            "pub fn a() -> i32 { let mut b = (10); b }\n",
+        )
+    })
+}
+
+/// Messages of the commits on top of the initial commit, newest first.
+fn commit_messages(repo: &git2::Repository) -> Vec<String> {
+    let mut walk = repo.revwalk().unwrap();
+    walk.push_head().unwrap();
+    let mut messages = walk
+        .map(|id| {
+            let commit = repo.find_commit(id.unwrap()).unwrap();
+            commit.message().unwrap().to_owned()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(messages.pop().as_deref(), Some("test"));
+    messages
+}
+
+fn assert_clean(repo: &git2::Repository) {
+    let statuses = repo
+        .statuses(Some(git2::StatusOptions::new().include_ignored(false)))
+        .unwrap();
+    let dirty = statuses
+        .iter()
+        .map(|status| status.path().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    assert!(dirty.is_empty(), "uncommitted changes to {dirty:?}");
+}
+
+const SINCE_SOURCE: &str = "\
+pub fn old() -> i32 {
+    let mut x = 1;
+    x
+}
+";
+
+fn since_project() -> (Project, git2::Repository) {
+    git::new_repo("foo", |p| {
+        p.file(".gitignore", "/target\n/Cargo.lock\n")
+            .file("src/lib.rs", SINCE_SOURCE)
+    })
+}
+
+#[cargo_test]
+fn since_fixes_changed_lines() {
+    let (p, _repo) = since_project();
+    p.change_file(
+        "src/lib.rs",
+        &format!("{SINCE_SOURCE}\npub fn new() -> i32 {{\n    let mut y = 2;\n    y\n}}\n"),
+    );
+
+    p.cargo_("fixit --since HEAD --allow-dirty")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[WARNING] variable does not need to be mutable
+ --> src/lib.rs:2:9
+  |
+2 |     let mut x = 1;
+  |         ----^
+  |         |
+  |         [HELP] remove this `mut`
+  |
+  = [NOTE] `#[warn(unused_mut)]` (part of `#[warn(unused)]`) on by default
+
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_e2e().eq(
+        p.read_file("src/lib.rs"),
+        str![[r#"
+pub fn old() -> i32 {
+    let mut x = 1;
+    x
+}
+
+pub fn new() -> i32 {
+    let y = 2;
+    y
+}
+
+"#]],
+    );
+}
+
+#[cargo_test]
+fn staged_fixes_staged_lines() {
+    let (p, repo) = since_project();
+    p.change_file(
+        "src/lib.rs",
+        &format!("{SINCE_SOURCE}\npub fn new() -> i32 {{\n    let mut y = 2;\n    y\n}}\n"),
+    );
+    git::add(&repo);
+    // Unstaged lines shift the staged ones further down
+    p.change_file(
+        "src/lib.rs",
+        &format!(
+            "//! Docs\n\n{SINCE_SOURCE}\npub fn new() -> i32 {{\n    let mut y = 2;\n    y\n}}\n"
+        ),
+    );
+
+    p.cargo_("fixit --staged --allow-dirty")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+...
+"#]])
+        .run();
+    assert_e2e().eq(
+        p.read_file("src/lib.rs"),
+        str![[r#"
+//! Docs
+
+pub fn old() -> i32 {
+    let mut x = 1;
+    x
+}
+
+pub fn new() -> i32 {
+    let y = 2;
+    y
+}
+
+"#]],
+    );
+}
+
+#[cargo_test]
+fn since_unknown_revision() {
+    let (p, _repo) = since_project();
+
+    p.cargo_("fixit --since nope")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[ERROR] failed to find git revision `nope`: revspec 'nope' not found; class=Reference (4); code=NotFound (-3)
+...
+"#]])
+        .run();
+}
+
+/// Puts a stand-in for a version control tool on `PATH` that prints `output`.
+#[cfg(unix)]
+fn fake_vcs_tool(p: &Project, program: &str, output: &str) -> std::ffi::OsString {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin = p.root().join("fake-bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let tool = bin.join(program);
+    std::fs::write(&tool, format!("#!/bin/sh\nprintf '{output}'\n")).unwrap();
+    std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let fixit = std::path::Path::new(env!("CARGO_BIN_EXE_cargo-fixit")).parent().unwrap();
+    let path = std::env::var_os("PATH").unwrap();
+    std::env::join_paths([bin, fixit.to_owned()].into_iter().chain(std::env::split_paths(&path)))
+        .unwrap()
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn mercurial_dirty_files() {
+    let p = project()
+        .file(".hg/requires", "")
+        .file("src/lib.rs", "pub fn foo() {}")
+        .build();
+    let path = fake_vcs_tool(&p, "hg", "M src/lib.rs\\n? notes.txt\\n");
+
+    p.cargo_("fixit")
+        .env("PATH", &path)
+        .with_status(4)
+        .with_stderr_data(str![[r#"
+[ERROR] the working directory of this package has uncommitted changes, and `cargo fix` can potentially perform destructive changes; if you'd like to suppress this error pass `--allow-dirty`, or commit the changes to these files:
+
+  * src/lib.rs (dirty)
+  * notes.txt (dirty)
+
+
+
+"#]])
+        .run();
+    p.cargo_("fixit --allow-dirty")
+        .env("PATH", &path)
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+
+"#]])
+        .run();
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn jujutsu_colocated_with_git() {
+    let p = git::new("foo", |p| {
+        p.file(".gitignore", "/target\n/Cargo.lock\n")
+            .file("src/lib.rs", "pub fn foo() {}")
+    });
+    std::fs::create_dir_all(p.root().join(".jj")).unwrap();
+    let path = fake_vcs_tool(&p, "jj", "src/lib.rs\\n");
+
+    p.cargo_("fixit")
+        .env("PATH", &path)
+        .with_status(4)
+        .with_stderr_data(str![[r#"
+[ERROR] the working directory of this package has uncommitted changes, and `cargo fix` can potentially perform destructive changes; if you'd like to suppress this error pass `--allow-dirty`, or commit the changes to these files:
+
+  * src/lib.rs (dirty)
+
+
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn skip_dirty_files() {
+    let p = git::new("foo", |p| {
+        p.file(".gitignore", "/target\n/Cargo.lock\n")
+            .file(
+                "src/lib.rs",
+                "pub mod clean;\npub fn dirty() -> i32 { let mut x = 1; x }\n",
+            )
+            .file("src/clean.rs", "pub fn clean() -> i32 { let mut y = 2; y }\n")
+    });
+    let dirty = "pub mod clean;\npub fn dirty() -> i32 { let mut x = 3; x }\n";
+    p.change_file("src/lib.rs", dirty);
+
+    p.cargo_("fixit --skip-dirty")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/clean.rs (1 fix)
+[WARNING] variable does not need to be mutable
+ --> src/lib.rs:2:29
+  |
+2 | pub fn dirty() -> i32 { let mut x = 3; x }
+  |                             ----^
+  |                             |
+  |                             [HELP] remove this `mut`
+  |
+  = [NOTE] `#[warn(unused_mut)]` (part of `#[warn(unused)]`) on by default
+
+[WARNING] skipped fixes to `src/lib.rs` as it has uncommitted changes
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), dirty);
+    assert_e2e().eq(p.read_file("src/clean.rs"), str![[r#"
+pub fn clean() -> i32 { let y = 2; y }
+
+"#]]);
+}
+
+#[cargo_test]
+fn sandbox_applies_fixes() {
+    let p = project()
+        .file("src/lib.rs", "pub fn foo() -> i32 { let mut x = 1; x }\n")
+        .build();
+
+    p.cargo_("fixit --sandbox")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r#"
+pub fn foo() -> i32 { let x = 1; x }
+
+"#]]);
+    // Built in a target directory of its own
+    assert!(!p.root().join("target/debug").exists());
+}
+
+#[cargo_test]
+fn sandbox_writes_only_to_target_dir() {
+    let p = project()
+        .file("src/lib.rs", "pub fn foo() -> i32 { let mut x = 1; x }\n")
+        .build();
+    let parent = p.root().parent().unwrap().to_owned();
+    // Cargo's home is in the same directory and may be created by the run.
+    std::fs::create_dir_all(cargo_test_support::paths::cargo_home()).unwrap();
+    let entries = |dir: &std::path::Path| {
+        let mut names = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+    let before = entries(&parent);
+
+    p.cargo_("fixit --sandbox").run();
+    assert_eq!(entries(&parent), before);
+    // The copy is removed once the fixes are applied, leaving its build and the recorded run.
+    assert_eq!(entries(&p.root().join("target/fixit")), ["runs", "sandbox"]);
+}
+
+#[cargo_test]
+fn sandbox_ancestor_config() {
+    let p = ancestor_config_project();
+
+    p.cargo_("fixit --sandbox")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r##"
+#[cfg(not(needed))]
+compile_error!("missing cfg");
+
+pub fn foo() -> i32 { let x = 1; x }
+
+"##]]);
+}
+
+#[cargo_test]
+fn sandbox_path_dependency_outside_workspace() {
+    let dep_source = "pub fn dep() -> i32 { let mut y = 1; y }\n";
+    let dep = project()
+        .at("dep")
+        .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+        .file("src/lib.rs", dep_source)
+        .build();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                "{}
+[dependencies]
+dep = {{ path = '../dep' }}
+",
+                basic_manifest("foo", "0.0.1")
+            ),
+        )
+        .file(
+            "src/lib.rs",
+            "pub fn foo() -> i32 { let mut x = dep::dep(); x }\n",
+        )
+        .build();
+
+    p.cargo_("fixit --sandbox")
+        .with_stderr_data(str![[r#"
+[WARNING] variable does not need to be mutable
+ --> [ROOT]/dep/src/lib.rs:1:27
+  |
+1 | pub fn dep() -> i32 { let mut y = 1; y }
+  |                           ----^
+  |                           |
+  |                           [HELP] remove this `mut`
+  |
+  = [NOTE] `#[warn(unused_mut)]` (part of `#[warn(unused)]`) on by default
+
+[CHECKING] dep v0.1.0
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r#"
+pub fn foo() -> i32 { let x = dep::dep(); x }
+
+"#]]);
+    assert_eq!(dep.read_file("src/lib.rs"), dep_source);
+}
+
+#[cargo_test]
+fn sandbox_run_can_be_undone() {
+    let original = "pub fn foo() -> i32 { let mut x = 1; x }\n";
+    let p = project().file("src/lib.rs", original).build();
+
+    p.cargo_("fixit --sandbox").run();
+    assert_eq!(
+        p.read_file("src/lib.rs"),
+        "pub fn foo() -> i32 { let x = 1; x }\n"
+    );
+
+    p.cargo_("fixit --undo")
+        .with_stderr_data(str![[r#"
+[NOTE] undoing the fixes in `src/lib.rs`
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), original);
+}
+
+#[cargo_test]
+fn sandbox_saves_patch() {
+    let original = "pub fn foo() -> i32 { let mut x = 1; x }\n";
+    let p = project().file("src/lib.rs", original).build();
+
+    p.cargo_("fixit --sandbox=fixes.patch")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+       Saved fixes as fixes.patch
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), original);
+    assert_e2e().eq(p.read_file("fixes.patch"), str![[r#"
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1 @@
+-pub fn foo() -> i32 { let mut x = 1; x }
++pub fn foo() -> i32 { let x = 1; x }
+
+"#]]);
+}
+
+#[cargo_test]
+fn fix_from_subdirectory() {
+    let p = project()
+        .file("src/lib.rs", "pub fn foo() -> i32 { let mut x = 1; x }\n")
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs")
+        .cwd(p.root().join("src"))
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r#"
+pub fn foo() -> i32 { let x = 1; x }
+
+"#]]);
+}
+
+const EDITION_SOURCE: &str = "\
+mod foo {
+    pub const FOO: &str = \"foo\";
+}
+
+pub fn bar() -> &'static str {
+    ::foo::FOO
+}
+";
+
+#[cargo_test]
+fn dry_run_edition_from_subdirectory() {
+    let manifest = r#"[package]
+name = "foo"
+version = "0.0.1"
+edition = "2015"
+"#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/lib.rs", EDITION_SOURCE)
+        .build();
+
+    p.cargo_("fixit --edition --dry-run")
+        .cwd(p.root().join("src"))
+        .with_stdout_data(str![[r#"
+--- a/lib.rs
++++ b/lib.rs
+@@ -3,5 +3,5 @@
+ }
+ 
+ pub fn bar() -> &'static str {
+-    ::foo::FOO
++    crate::foo::FOO
+ }
+--- a/Cargo.toml
++++ b/Cargo.toml
+@@ -1,4 +1,4 @@
+ [package]
+ name = "foo"
+ version = "0.0.1"
+-edition = "2015"
++edition = "2018"
+
+"#]])
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] lib.rs (1 fix)
+[MIGRATING] Cargo.toml from 2015 edition to 2018
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("Cargo.toml"), manifest);
+    assert_eq!(p.read_file("src/lib.rs"), EDITION_SOURCE);
+}
+
+#[cargo_test]
+fn commit_edition_from_subdirectory() {
+    let (p, repo) = git::new_repo("foo", |p| {
+        p.file(".gitignore", "/target\n/Cargo.lock\n")
+            .file("src/lib.rs", EDITION_SOURCE)
+    });
+
+    p.cargo_("fixit --edition --commit single")
+        .cwd(p.root().join("src"))
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] lib.rs (1 fix)
+[MIGRATING] Cargo.toml from 2015 edition to 2018
+   Committed Fix warnings (1 fix in 2 files)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_eq!(commit_messages(&repo), ["Fix warnings (1 fix in 2 files)"]);
+    assert_clean(&repo);
+}
+
+#[cargo_test]
+fn fix_each_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"[package]
+name = "foo"
+version = "0.1.0"
+edition = "2021"
+
+[features]
+a = []
+b = ["a"]
+"#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"#[cfg(feature = "a")]
+pub fn a() -> usize { let mut value = 1; value }
+
+#[cfg(feature = "b")]
+pub fn b() -> usize { let mut value = 2; value }
+
+#[cfg(not(feature = "a"))]
+pub fn none() -> usize { let mut value = 3; value }
+"#,
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --each-feature")
+        .with_stderr_data(str![[r#"
+[FIXING] foo with no features
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+[FIXING] foo with feature `a`
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+[FIXING] foo with feature `b`
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+[SUMMARY] foo with no features: 1 fix, 0 warnings remaining
+[SUMMARY] foo with feature `a`: 1 fix, 0 warnings remaining
+[SUMMARY] foo with feature `b`: 1 fix, 0 warnings remaining
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r#"
+#[cfg(feature = "a")]
+pub fn a() -> usize { let value = 1; value }
+
+#[cfg(feature = "b")]
+pub fn b() -> usize { let value = 2; value }
+
+#[cfg(not(feature = "a"))]
+pub fn none() -> usize { let value = 3; value }
+
+"#]]);
+}
+
+#[cargo_test]
+fn fix_feature_powerset_warns_of_many_combinations() {
+    let features = ["a", "b", "c", "d", "e", "f", "g", "h"]
+        .map(|name| format!("{name} = []\n"))
+        .concat();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!("{}\n[features]\n{features}", basic_manifest("foo", "0.1.0")),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --feature-powerset")
+        .with_stderr_data(str![[r#"
+[WARNING] fixing `foo` with 37 combinations of features
+[NOTE] pass a lower `--depth` to combine fewer features at a time
+...
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn fix_each_feature_warns_of_many_combinations() {
+    let features = (0..31).map(|i| format!("f{i} = []\n")).collect::<String>();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!("{}\n[features]\n{features}", basic_manifest("foo", "0.1.0")),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --each-feature")
+        .with_stderr_data(str![[r#"
+[WARNING] fixing `foo` with 33 combinations of features
+[FIXING] foo with no features
+...
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn fix_feature_powerset() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"[package]
+name = "foo"
+version = "0.1.0"
+edition = "2021"
+
+[features]
+a = []
+b = []
+c = []
+"#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"#[cfg(all(feature = "a", feature = "b"))]
+pub fn ab() -> usize { let mut value = 1; value }
+
+#[cfg(all(feature = "a", feature = "b", feature = "c"))]
+pub fn abc() -> usize { let mut value = 2; value }
+
+fn unused() {}
+"#,
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --feature-powerset")
+        .with_stderr_data(str![[r#"
+[FIXING] foo with no features
+[CHECKING] foo v0.1.0
+[WARNING] function `unused` is never used
+ --> src/lib.rs:7:4
+  |
+7 | fn unused() {}
+  |    ^^^^^^
+  |
+  = [NOTE] `#[warn(dead_code)]` (part of `#[warn(unused)]`) on by default
+
+[FIXING] foo with feature `a`
+[CHECKING] foo v0.1.0
+[WARNING] function `unused` is never used
+ --> src/lib.rs:7:4
+  |
+7 | fn unused() {}
+  |    ^^^^^^
+  |
+  = [NOTE] `#[warn(dead_code)]` (part of `#[warn(unused)]`) on by default
+
+[FIXING] foo with feature `b`
+[CHECKING] foo v0.1.0
+[WARNING] function `unused` is never used
+ --> src/lib.rs:7:4
+  |
+7 | fn unused() {}
+  |    ^^^^^^
+  |
+  = [NOTE] `#[warn(dead_code)]` (part of `#[warn(unused)]`) on by default
+
+[FIXING] foo with feature `c`
+[CHECKING] foo v0.1.0
+[WARNING] function `unused` is never used
+ --> src/lib.rs:7:4
+  |
+7 | fn unused() {}
+  |    ^^^^^^
+  |
+  = [NOTE] `#[warn(dead_code)]` (part of `#[warn(unused)]`) on by default
+
+[FIXING] foo with features `a`, `b`
+[CHECKING] foo v0.1.0
+[FIXED] src/lib.rs (1 fix)
+[WARNING] function `unused` is never used
+ --> src/lib.rs:7:4
+  |
+7 | fn unused() {}
+  |    ^^^^^^
+  |
+  = [NOTE] `#[warn(dead_code)]` (part of `#[warn(unused)]`) on by default
+
+[FIXING] foo with features `a`, `c`
+[CHECKING] foo v0.1.0
+[WARNING] function `unused` is never used
+ --> src/lib.rs:7:4
+  |
+7 | fn unused() {}
+  |    ^^^^^^
+  |
+  = [NOTE] `#[warn(dead_code)]` (part of `#[warn(unused)]`) on by default
+
+[FIXING] foo with features `b`, `c`
+[CHECKING] foo v0.1.0
+[WARNING] function `unused` is never used
+ --> src/lib.rs:7:4
+  |
+7 | fn unused() {}
+  |    ^^^^^^
+  |
+  = [NOTE] `#[warn(dead_code)]` (part of `#[warn(unused)]`) on by default
+
+[SUMMARY] foo with no features: 0 fixes, 1 warning remaining
+[SUMMARY] foo with feature `a`: 0 fixes, 1 warning remaining
+[SUMMARY] foo with feature `b`: 0 fixes, 1 warning remaining
+[SUMMARY] foo with feature `c`: 0 fixes, 1 warning remaining
+[SUMMARY] foo with features `a`, `b`: 1 fix, 1 warning remaining
+[SUMMARY] foo with features `a`, `c`: 0 fixes, 1 warning remaining
+[SUMMARY] foo with features `b`, `c`: 0 fixes, 1 warning remaining
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r##"
+#[cfg(all(feature = "a", feature = "b"))]
+pub fn ab() -> usize { let value = 1; value }
+
+#[cfg(all(feature = "a", feature = "b", feature = "c"))]
+pub fn abc() -> usize { let mut value = 2; value }
+
+fn unused() {}
+
+"##]]);
+}
+
+#[cargo_test]
+fn verify_failure_reverts_fixes() {
+    let original = "pub fn foo() -> i32 { let mut x = 1; x }\n";
+    let p = project()
+        .file("src/lib.rs", original)
+        .file(
+            "tests/source.rs",
+            r#"#[test]
+fn unchanged() { assert!(include_str!("../src/lib.rs").contains("let mut")); }
+"#,
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs")
+        .arg("--verify=cargo test --test source")
+        .with_status(9)
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[VERIFYING] cargo test --test source
+...
+[NOTE] reverting `src/lib.rs` to its original state
+[ERROR] `cargo test --test source` failed after the fixes were applied
+
+"#]])
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), original);
+}
+
+#[cargo_test]
+fn verify_bisect_keeps_passing_fixes() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "mod other;\npub fn foo() -> i32 { let mut x = 1; x }\n",
+        )
+        .file(
+            "src/other.rs",
+            "pub fn bar() -> i32 { let mut y = 2; y }\n",
+        )
+        .file(
+            "tests/source.rs",
+            r#"#[test]
+fn unchanged() { assert!(include_str!("../src/lib.rs").contains("let mut")); }
+"#,
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --bisect")
+        .arg("--verify=cargo test --test source")
+        .with_stderr_data(str![[r#"
+...
+[NOTE] reverting `src/lib.rs` to its original state
+[WARNING] reverted the fixes to 1 file as `cargo test --test source` failed with them
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r#"
+mod other;
+pub fn foo() -> i32 { let mut x = 1; x }
+
+"#]]);
+    assert_e2e().eq(p.read_file("src/other.rs"), str![[r#"
+pub fn bar() -> i32 { let y = 2; y }
+
+"#]]);
+}
+
+#[cargo_test]
+fn speculative_keeps_fixes_that_compile() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"fn check() -> Result<(), ()> { Ok(()) }
+
+fn Helper() -> i32 { 1 }
+
+pub fn foo() -> i32 {
+    check();
+    Helper()
+}
+"#,
+        )
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --speculative")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+[WARNING] function `Helper` should have a snake case name
+ --> src/lib.rs:3:4
+  |
+3 | fn Helper() -> i32 { 1 }
+  |    ^^^^^^ [HELP] convert the identifier to snake case: `helper`
+  |
+  = [NOTE] `#[warn(non_snake_case)]` (part of `#[warn(nonstandard_style)]`) on by default
+
+[WARNING] `src/lib.rs` has fixes that are not machine-applicable and should be reviewed
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r#"
+fn check() -> Result<(), ()> { Ok(()) }
+
+fn Helper() -> i32 { 1 }
+
+pub fn foo() -> i32 {
+    let _ = check();
+    Helper()
+}
+
+"#]]);
+}
+
+#[cargo_test]
+fn fmt_formats_fixed_files() {
+    let p = project()
+        .file("rustfmt.toml", "tab_spaces = 2\n")
+        .file(
+            "src/lib.rs",
+            "pub mod other;\npub fn foo() -> i32 { let mut x = 1; x }\n",
+        )
+        .file("src/other.rs", "pub fn bar() -> i32 {   2   }\n")
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --fmt")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] src/lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r#"
+pub mod other;
+pub fn foo() -> i32 {
+  let x = 1;
+  x
+}
+
+"#]]);
+    assert_e2e().eq(p.read_file("src/other.rs"), str![[r#"
+pub fn bar() -> i32 {   2   }
+
+"#]]);
+}
+
+#[cargo_test]
+fn fmt_from_subdirectory() {
+    let p = project()
+        .file("src/lib.rs", "pub fn foo() -> i32 { let mut x = 1; x }\n")
+        .build();
+
+    p.cargo_("fixit --allow-no-vcs --fmt")
+        .cwd(p.root().join("src"))
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[FIXED] lib.rs (1 fix)
+    Recorded run 1, undo it with `cargo fixit --undo 1`
+
+"#]])
+        .run();
+
+    assert_e2e().eq(p.read_file("src/lib.rs"), str![[r#"
+pub fn foo() -> i32 {
+    let x = 1;
+    x
+}
+
+"#]]);
+}
+
+#[cargo_test]
+fn fmt_failure_reverts_fixes() {
+    let original = "pub fn foo() -> i32 { let mut x = 1; x }\n";
+    let p = project().file("src/lib.rs", original).build();
+
+    p.cargo_("fixit --allow-no-vcs --fmt")
+        .env("RUSTFMT", "false")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1
+[ERROR] rustfmt failed to format `src/lib.rs`
+
+
+"#]])
+        .run();
+
+    assert_eq!(p.read_file("src/lib.rs"), original);
+}